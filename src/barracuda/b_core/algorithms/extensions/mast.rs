@@ -1,7 +1,7 @@
 use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
 use crate::barracuda::b_core::components::policy::Policy;
 use crate::barracuda::b_core::params::{BarracudaSimulationParams, BarracudaUcbParams};
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 use crate::barracuda::traits::{
     BarracudaAlgorithm, BarracudaBoard, BarracudaMove, BarracudaParams, Player,
 };
@@ -42,15 +42,15 @@ impl<
 {
     fn set_node(
         &mut self,
-        state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>,
+        state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>,
         last_move: &Move,
     ) {
         let move_map_score = self
             .move_map
             .lock()
             .unwrap()
-            .get(state.board.turn(), *last_move);
-        let visits = state.visits;
+            .get(state.board().turn(), *last_move);
+        let visits = state.visits.get();
         *self.eval.lock().unwrap() = move_map_score.unwrap_or(0f32) / visits;
     }
 }
@@ -65,14 +65,14 @@ impl<
 {
     fn set_node(
         &mut self,
-        state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>,
+        state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>,
         last_move: &Move,
         eval: f32,
     ) {
         self.move_map
             .lock()
             .unwrap()
-            .add(state.board.turn(), *last_move, eval);
+            .add(state.board().turn(), *last_move, eval);
     }
 }
 
@@ -1,5 +1,5 @@
 use crate::barracuda::b_core::params::{BarracudaSimulationParams, BarracudaUcbParams};
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 use crate::barracuda::traits::{
     BarracudaAlgorithm, BarracudaBoard, BarracudaMove, BarracudaParams,
 };
@@ -39,9 +39,9 @@ impl<
         const DATA_REGISTERS: usize,
     > BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }> for Rave<Board, Params, Move>
 {
-    fn set_node(&mut self, state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
-        self.eval =
-            state.registers[self.score_register] / (state.registers[self.visit_register] + 1e-8);
+    fn set_node(&mut self, state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
+        self.eval = state.registers[self.score_register].get()
+            / (state.registers[self.visit_register].get() + 1e-8);
     }
 }
 
@@ -55,43 +55,31 @@ impl<
 {
     fn set_node(
         &mut self,
-        state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>,
+        state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>,
         last_move: &Move,
         eval: f32,
     ) {
-        state.registers[self.score_register] += eval;
-        state.registers[self.visit_register] += 1f32;
+        state.registers[self.score_register].add(eval);
+        state.registers[self.visit_register].add(1f32);
 
         let mut skips = 0u16;
-        if let Some(mut parent) = state.parent.clone() {
-            loop {
-                if let Some(u_parent) = parent.upgrade() {
-                    skips += 1;
-                    let mut parent_lock = u_parent.lock().unwrap();
-                    if skips > 2 && skips % 2 == 0 {
-                        let mut found = false;
-                        for mv in parent_lock.moves.iter() {
-                            if *mv == *last_move {
-                                found = true;
-                                break;
-                            }
-                        }
-                        if found {
-                            parent_lock.registers[self.score_register] += eval;
-                            parent_lock.registers[self.visit_register] += 1f32;
-                        }
-                    }
-                    let node_parent = parent_lock.parent.clone();
-                    drop(parent_lock);
-                    if let Some(node_parent) = node_parent {
-                        parent = node_parent;
-                    } else {
+        let mut parent = state.parent();
+        while let Some(u_parent) = parent {
+            skips += 1;
+            if skips > 2 && skips % 2 == 0 {
+                let mut found = false;
+                for mv in u_parent.moves.iter() {
+                    if *mv == *last_move {
+                        found = true;
                         break;
                     }
-                } else {
-                    println!("WARNING: Parent Node has been dropped")
+                }
+                if found {
+                    u_parent.registers[self.score_register].add(eval);
+                    u_parent.registers[self.visit_register].add(1f32);
                 }
             }
+            parent = u_parent.parent();
         }
     }
 }
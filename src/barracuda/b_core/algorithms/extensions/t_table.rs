@@ -6,7 +6,7 @@ use std::collections::HashMap;
 use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
 use crate::barracuda::b_core::components::backprop::Backprop;
 use crate::barracuda::b_core::params::BarracudaUcbParams;
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 use std::marker::PhantomData;
 
 pub struct TranspositionTable<
@@ -57,8 +57,8 @@ impl<
     > Backprop<Board, Params, Move, { DATA_REGISTERS }>
     for TranspositionTable<Board, Params, Move, { DATA_REGISTERS }>
 {
-    fn backprop(&mut self, node: &mut Node<Board, Params, Move, { DATA_REGISTERS }>, score: f32) {
-        if let Some(entry) = self.table.get_mut(&node.board) {
+    fn backprop(&mut self, node: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, score: f32) {
+        if let Some(entry) = self.table.get_mut(&*node.board()) {
             entry.0 += score;
             entry.1 += 1f32;
         }
@@ -73,8 +73,8 @@ impl<
     > BarracudaUcbParams<Board, Params, Move, DATA_REGISTERS>
     for TranspositionTable<Board, Params, Move, { DATA_REGISTERS }>
 {
-    fn set_node(&mut self, state: &mut Node<Board, Params, Move, DATA_REGISTERS>, _: &Move) {
-        let eval = if let Some((score, visits)) = self.table.get(&state.board) {
+    fn set_node(&mut self, state: NodeRef<Board, Params, Move, DATA_REGISTERS>, _: &Move) {
+        let eval = if let Some((score, visits)) = self.table.get(&*state.board()) {
             1f32 - *score / *visits
         } else {
             0f32
@@ -1,10 +1,9 @@
 use crate::barracuda::b_core::components::policy::Policy;
 use crate::barracuda::b_core::components::static_eval::StaticEval;
+use crate::barracuda::mcts::sample_outcome;
 use crate::barracuda::traits::{
     BarracudaAlgorithm, BarracudaBoard, BarracudaMove, BarracudaParams,
 };
-use rand::distributions::Uniform;
-use rand::Rng;
 use std::sync::{Arc, Mutex};
 
 pub struct Softmax<
@@ -32,31 +31,35 @@ impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: Barracu
     Policy<Board, Params, Move> for Softmax<Board, Params, Move>
 {
     fn pick(&mut self, board: &Board, moves: &[Move]) -> usize {
+        sample_outcome(self.priors(board, moves))
+    }
+
+    /// Softmax over the evaluation of the position after each move, for the player making it.
+    fn priors(&mut self, board: &Board, moves: &[Move]) -> Vec<f32> {
         let mut move_scores = vec![];
-        let mut min = f32::INFINITY;
+        let mut max = f32::NEG_INFINITY;
 
         let mut eval = self.eval.lock().unwrap();
         for mv in moves {
-            let mut board = board.clone();
-            board.make_move(*mv);
-            let score = eval.evaluate(&board);
+            let mut new_board = board.clone();
+            new_board.make_move(*mv);
+            let score = eval.evaluate(&new_board);
+            let score = if new_board.turn() == board.turn() {
+                score
+            } else {
+                -score
+            };
             move_scores.push(score);
-            min = min.min(score);
+            max = max.max(score);
         }
         let mut sum = 0f32;
         for score in &mut move_scores {
-            *score = (*score - min).exp();
+            *score = (*score - max).exp();
             sum += *score;
         }
         for score in &mut move_scores {
             *score /= sum;
         }
-        let distr = Uniform::new(0f32, 1f32);
-        let random = rand::thread_rng().sample(distr);
-        let mut index = 0;
-        while index < move_scores.len() - 1 && random > move_scores[index] {
-            index += 1;
-        }
-        index
+        move_scores
     }
 }
@@ -1,5 +1,6 @@
 use crate::barracuda::b_core::components::policy::Policy;
 use crate::barracuda::b_core::components::simulate::Simulation;
+use crate::barracuda::mcts::sample_outcome;
 use crate::barracuda::traits::GameState;
 use crate::barracuda::traits::{
     BarracudaAlgorithm, BarracudaBoard, BarracudaMove, BarracudaParams, Player,
@@ -13,6 +14,8 @@ pub struct RandomPlayout<
 > {
     depth: usize,
     policy: Arc<Mutex<dyn Policy<Board, Params, Move>>>,
+    margin: Option<f32>,
+    rewards: Vec<f32>,
 }
 
 impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove>
@@ -24,7 +27,12 @@ impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: Barracu
     RandomPlayout<Board, Params, Move>
 {
     pub fn new(depth: usize, policy: Arc<Mutex<dyn Policy<Board, Params, Move>>>) -> Self {
-        Self { depth, policy }
+        Self {
+            depth,
+            policy,
+            margin: None,
+            rewards: vec![],
+        }
     }
 
     pub fn eval(&mut self, board: &Board) -> f32 {
@@ -34,7 +42,7 @@ impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: Barracu
                 None => 0.5,
                 Some(color) => match color {
                     Player::P1 => 1.0,
-                    Player::P2 => 0.0,
+                    _ => 0.0,
                 },
             },
         }
@@ -55,6 +63,12 @@ impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: Barracu
             if !matches!(game_state, GameState::Ongoing) {
                 break;
             }
+            let outcomes = board.chance_outcomes();
+            if !outcomes.is_empty() {
+                let outcome = sample_outcome(outcomes.iter().map(|(_, probability)| *probability));
+                board.make_move(outcomes[outcome].0);
+                continue;
+            }
             let moves = board.get_moves();
             if moves.is_empty() {
                 break;
@@ -63,6 +77,18 @@ impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: Barracu
             board.make_move(moves[index]);
             depth += 1;
         }
+        self.margin = board.terminal_score();
+        if board.players() > 2 {
+            self.rewards = board.rewards();
+        }
         self.eval(&board)
     }
+
+    fn margin(&self) -> Option<f32> {
+        self.margin
+    }
+
+    fn rewards(&self) -> Vec<f32> {
+        self.rewards.clone()
+    }
 }
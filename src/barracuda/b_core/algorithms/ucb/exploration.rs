@@ -1,7 +1,7 @@
 use crate::barracuda::b_core::params::BarracudaUcbParams;
 use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
 
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 
 use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
 
@@ -26,18 +26,9 @@ impl<
         const DATA_REGISTERS: usize,
     > BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }> for Exploration
 {
-    fn set_node(&mut self, state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
-        let parent_visits = if let Some(ref parent) = state.parent {
-            if let Some(strong) = parent.upgrade() {
-                strong.lock().unwrap().visits
-            } else {
-                println!("# WARNING PARENT NODE HAS BEEN DROPPED");
-                0f32
-            }
-        } else {
-            0f32
-        };
-        self.exploration = self.temperature * (parent_visits.ln() / state.visits).sqrt();
+    fn set_node(&mut self, state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
+        let parent_visits = state.parent_visits();
+        self.exploration = self.temperature * (parent_visits.ln() / state.visits.get()).sqrt();
     }
 }
 
@@ -0,0 +1,450 @@
+use crate::barracuda::b_core::algorithms::ucb::formula::{
+    Add, Const, Div, Formula, Ln, Max, Min, Mul, Neg, Pow, Sqrt, Sub,
+};
+use crate::barracuda::b_core::params::BarracudaUcbParams;
+use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Mutex};
+
+type SharedFormula = Arc<Mutex<dyn Formula>>;
+
+type UcbAlgorithm<Board, Params, Move, const DATA_REGISTERS: usize> =
+    Arc<Mutex<dyn BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }>>>;
+
+/// Why an expression couldn't be parsed, `position` being the character it happened at,
+/// counting from 0.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub position: usize,
+    pub message: String,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at position {}", self.message, self.position)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// A parsed UCB formula along with the components it uses, to be scored with each child.
+pub struct Expression<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    pub formula: SharedFormula,
+    /// UCB algorithms of `BarracudaAlgorithms`, in the order they first appear.
+    pub ucb_algorithms: Vec<UcbAlgorithm<Board, Params, Move, DATA_REGISTERS>>,
+}
+
+struct Entry<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    formula: SharedFormula,
+    ucb: Option<UcbAlgorithm<Board, Params, Move, DATA_REGISTERS>>,
+}
+
+/// Names UCB components so formulas can be written as text, like
+/// `value + 1.41 * explore + 0.1 * rave + mast`. Besides names and constants, expressions have
+/// `+ - * /`, parentheses and the functions `sqrt(x)`, `ln(x)`, `pow(x, y)`, `min(x, y, ...)`
+/// and `max(x, y, ...)`. A name used twice is the same component.
+pub struct FormulaRegistry<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    entries: HashMap<String, Entry<Board, Params, Move, DATA_REGISTERS>>,
+}
+
+impl<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > FormulaRegistry<Board, Params, Move, DATA_REGISTERS>
+{
+    pub fn new() -> Self {
+        Self {
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Makes `component` available as `name`, set to each child before it is scored.
+    pub fn register<
+        T: Formula + BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }> + 'static,
+    >(
+        &mut self,
+        name: &str,
+        component: Arc<Mutex<T>>,
+    ) {
+        let entry = Entry {
+            formula: component.clone(),
+            ucb: Some(component),
+        };
+        self.entries.insert(name.to_string(), entry);
+    }
+
+    /// Makes `formula` available as `name` without setting it to the children, for formulas that
+    /// need no node, like `Random`, or get theirs elsewhere.
+    pub fn register_formula(&mut self, name: &str, formula: SharedFormula) {
+        let entry = Entry { formula, ucb: None };
+        self.entries.insert(name.to_string(), entry);
+    }
+
+    pub fn parse(
+        &self,
+        expression: &str,
+    ) -> Result<Expression<Board, Params, Move, DATA_REGISTERS>, ParseError> {
+        let mut parser = Parser {
+            registry: self,
+            chars: expression.chars().collect(),
+            position: 0,
+            ucb_algorithms: vec![],
+        };
+        let formula = parser.sum()?;
+        parser.skip_whitespace();
+        if let Some(c) = parser.peek() {
+            return Err(parser.error(format!("Unexpected '{}' after the expression", c)));
+        }
+        Ok(Expression {
+            formula,
+            ucb_algorithms: parser.ucb_algorithms,
+        })
+    }
+}
+
+impl<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > Default for FormulaRegistry<Board, Params, Move, DATA_REGISTERS>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn shared(formula: impl Formula + 'static) -> SharedFormula {
+    Arc::new(Mutex::new(formula))
+}
+
+/// Parts joined by `+` or `*` go into a single `Add` or `Mul`.
+fn join(mut parts: Vec<SharedFormula>, product: bool) -> SharedFormula {
+    match (parts.len(), product) {
+        (1, _) => parts.pop().unwrap(),
+        (_, false) => shared(Add::new(parts)),
+        (_, true) => shared(Mul::new(parts)),
+    }
+}
+
+/// Recursive descent over `sum := product (('+' | '-') product)*`,
+/// `product := unary (('*' | '/') unary)*`, `unary := '-' unary | primary` and
+/// `primary := number | name | name '(' sum (',' sum)* ')' | '(' sum ')'`.
+struct Parser<
+    'a,
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    registry: &'a FormulaRegistry<Board, Params, Move, DATA_REGISTERS>,
+    chars: Vec<char>,
+    position: usize,
+    ucb_algorithms: Vec<UcbAlgorithm<Board, Params, Move, DATA_REGISTERS>>,
+}
+
+impl<
+        'a,
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > Parser<'a, Board, Params, Move, DATA_REGISTERS>
+{
+    fn error(&self, message: String) -> ParseError {
+        ParseError {
+            position: self.position,
+            message,
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(char::is_whitespace) {
+            self.position += 1;
+        }
+    }
+
+    /// Consumes `c` if it is the next character after any whitespace.
+    fn eat(&mut self, c: char) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn sum(&mut self) -> Result<SharedFormula, ParseError> {
+        self.binary(false)
+    }
+
+    /// A sum, or a product if `product` is set.
+    fn binary(&mut self, product: bool) -> Result<SharedFormula, ParseError> {
+        let (join_op, split_op) = if product { ('*', '/') } else { ('+', '-') };
+        let operand = |parser: &mut Self| {
+            if product {
+                parser.unary()
+            } else {
+                parser.binary(true)
+            }
+        };
+        let mut parts = vec![operand(self)?];
+        loop {
+            if self.eat(join_op) {
+                parts.push(operand(self)?);
+            } else if self.eat(split_op) {
+                let left = join(parts, product);
+                let right = operand(self)?;
+                parts = vec![if product {
+                    shared(Div::new(left, right))
+                } else {
+                    shared(Sub::new(left, right))
+                }];
+            } else {
+                return Ok(join(parts, product));
+            }
+        }
+    }
+
+    fn unary(&mut self) -> Result<SharedFormula, ParseError> {
+        if !self.eat('-') {
+            return self.primary();
+        }
+        self.skip_whitespace();
+        //Negative constants stay constants
+        if self.peek().is_some_and(|c| c.is_ascii_digit() || c == '.') {
+            return Ok(Const::new(-self.number()?));
+        }
+        Ok(shared(Neg::new(self.unary()?)))
+    }
+
+    fn primary(&mut self) -> Result<SharedFormula, ParseError> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(c) if c.is_ascii_digit() || c == '.' => Ok(Const::new(self.number()?)),
+            Some(c) if c.is_alphabetic() || c == '_' => self.name(),
+            Some('(') => {
+                self.position += 1;
+                let formula = self.sum()?;
+                self.expect(')')?;
+                Ok(formula)
+            }
+            Some(c) => Err(self.error(format!("Expected an expression, found '{}'", c))),
+            None => Err(self.error("Expected an expression, found the end".to_string())),
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ParseError> {
+        if self.eat(c) {
+            return Ok(());
+        }
+        match self.peek() {
+            Some(found) => Err(self.error(format!("Expected '{}', found '{}'", c, found))),
+            None => Err(self.error(format!("Expected '{}', found the end", c))),
+        }
+    }
+
+    /// Digits with an optional fraction and exponent, like `1.41`, `.5` or `1e-3`.
+    fn number(&mut self) -> Result<f32, ParseError> {
+        let start = self.position;
+        let digits = |parser: &mut Self| {
+            while parser.peek().is_some_and(|c| c.is_ascii_digit()) {
+                parser.position += 1;
+            }
+        };
+        digits(self);
+        if self.peek() == Some('.') {
+            self.position += 1;
+            digits(self);
+        }
+        if matches!(self.peek(), Some('e' | 'E')) {
+            let mantissa = self.position;
+            self.position += 1;
+            if matches!(self.peek(), Some('+' | '-')) {
+                self.position += 1;
+            }
+            if self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                digits(self);
+            } else {
+                //Not an exponent after all
+                self.position = mantissa;
+            }
+        }
+        let text = self.chars[start..self.position].iter().collect::<String>();
+        text.parse::<f32>().map_err(|_| ParseError {
+            position: start,
+            message: format!("Invalid number '{}'", text),
+        })
+    }
+
+    /// A registered component, or a function call if an opening parenthesis follows right away.
+    fn name(&mut self) -> Result<SharedFormula, ParseError> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.position += 1;
+        }
+        let name = self.chars[start..self.position].iter().collect::<String>();
+        //Right after the name, `a (b)` being a name followed by a stray parenthesis
+        if self.peek() == Some('(') {
+            self.position += 1;
+            return self.call(&name, start);
+        }
+        let Some(entry) = self.registry.entries.get(&name) else {
+            return Err(ParseError {
+                position: start,
+                message: format!("Unknown name '{}'", name),
+            });
+        };
+        if let Some(ucb) = &entry.ucb {
+            if !self
+                .ucb_algorithms
+                .iter()
+                .any(|algorithm| Arc::ptr_eq(algorithm, ucb))
+            {
+                self.ucb_algorithms.push(ucb.clone());
+            }
+        }
+        Ok(entry.formula.clone())
+    }
+
+    /// Arguments of the function `name` at `start`, after its opening parenthesis.
+    fn call(&mut self, name: &str, start: usize) -> Result<SharedFormula, ParseError> {
+        //Before the arguments, so their errors don't hide a misspelled function
+        if !matches!(name, "sqrt" | "ln" | "pow" | "min" | "max") {
+            return Err(ParseError {
+                position: start,
+                message: format!("Unknown function '{}'", name),
+            });
+        }
+        let mut arguments = vec![self.sum()?];
+        while self.eat(',') {
+            arguments.push(self.sum()?);
+        }
+        self.expect(')')?;
+        let count = arguments.len();
+        let arity = |expected: &str| ParseError {
+            position: start,
+            message: format!("'{}' takes {}, got {}", name, expected, count),
+        };
+        match name {
+            "sqrt" if count == 1 => Ok(shared(Sqrt::new(arguments.remove(0)))),
+            "ln" if count == 1 => Ok(shared(Ln::new(arguments.remove(0)))),
+            "pow" if count == 2 => {
+                let exponent = arguments.remove(1);
+                Ok(shared(Pow::new(arguments.remove(0), exponent)))
+            }
+            "min" if count >= 2 => Ok(shared(Min::new(arguments))),
+            "max" if count >= 2 => Ok(shared(Max::new(arguments))),
+            "sqrt" | "ln" => Err(arity("1 argument")),
+            "pow" => Err(arity("2 arguments")),
+            _ => Err(arity("at least 2 arguments")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::barracuda::b_core::algorithms::ucb::exploration::Exploration;
+    use crate::barracuda::b_core::algorithms::ucb::expression::FormulaRegistry;
+    use crate::barracuda::b_core::algorithms::ucb::formula::{Const, Formula};
+    use crate::barracuda::b_core::algorithms::ucb::value::Value;
+    use crate::tictactoe::ttt::{Square, TicTacToeBoard, TicTacToeParams};
+    use std::sync::{Arc, Mutex};
+
+    type Registry = FormulaRegistry<TicTacToeBoard, TicTacToeParams, Square, 0>;
+
+    #[test]
+    fn formula_expressions_parse_into_formulas() {
+        let mut registry = Registry::new();
+        registry.register_formula("a", Const::new(2.0));
+        registry.register_formula("b", Const::new(3.0));
+        let value = |expression: &str| registry.parse(expression).unwrap().formula.get();
+        assert_eq!(value("a + b * 2"), 8.0);
+        assert_eq!(value("(a + b) * 2"), 10.0);
+        assert_eq!(value("a - b - 1"), -2.0);
+        assert_eq!(value("b / a / 2"), 0.75);
+        assert_eq!(value("-a + -1.5 - -b"), -0.5);
+        assert_eq!(value("sqrt(pow(b, 2)) + ln(1)"), 3.0);
+        assert_eq!(value("min(a, b, 1) + max(a, b)"), 4.0);
+        assert_eq!(value("1e-3 * 1000 + .5"), 1.5);
+        let term = registry.parse("a + 0.5 * b").unwrap().formula.explain();
+        let labels = term
+            .terms
+            .iter()
+            .map(|term| &term.label)
+            .collect::<Vec<_>>();
+        assert_eq!(labels, ["2", "Mul"]);
+        assert_eq!(term.terms[1].terms[0].label, "0.5");
+
+        let error = |expression: &str| {
+            let error = registry.parse(expression).err().unwrap();
+            (error.position, error.message)
+        };
+        assert_eq!(
+            error("a + "),
+            (4, "Expected an expression, found the end".to_string())
+        );
+        assert_eq!(error("a + c"), (4, "Unknown name 'c'".to_string()));
+        assert_eq!(
+            error("2 * sqrt(a, b)"),
+            (4, "'sqrt' takes 1 argument, got 2".to_string())
+        );
+        assert_eq!(
+            error("(a + b"),
+            (6, "Expected ')', found the end".to_string())
+        );
+        assert_eq!(
+            error("a b"),
+            (2, "Unexpected 'b' after the expression".to_string())
+        );
+        assert_eq!(
+            error("exp(a + c)"),
+            (0, "Unknown function 'exp'".to_string())
+        );
+        assert_eq!(
+            error("a (b)"),
+            (2, "Unexpected '(' after the expression".to_string())
+        );
+        assert_eq!(
+            error("a * $"),
+            (4, "Expected an expression, found '$'".to_string())
+        );
+        assert_eq!(
+            registry.parse("a +").err().unwrap().to_string(),
+            "Expected an expression, found the end at position 3"
+        );
+    }
+
+    #[test]
+    fn names_used_twice_are_the_same_component() {
+        let mut registry = Registry::new();
+        registry.register("value", Arc::new(Mutex::new(Value::new())));
+        registry.register("explore", Arc::new(Mutex::new(Exploration::new(1.0))));
+        registry.register_formula("c", Const::new(1.0));
+        let expression = registry.parse("value + c * explore + 0 * value").unwrap();
+        assert_eq!(expression.ucb_algorithms.len(), 2);
+    }
+}
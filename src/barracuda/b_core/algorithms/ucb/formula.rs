@@ -1,7 +1,108 @@
+use std::fmt::{Display, Formatter};
 use std::sync::{Arc, Mutex};
 
 pub trait Formula: Send {
     fn get(&self) -> f32;
+
+    /// Name of the formula in a breakdown, its type without the path and generics by default.
+    fn label(&self) -> String {
+        let name = std::any::type_name::<Self>();
+        let name = name.split('<').next().unwrap_or(name);
+        name.rsplit("::").next().unwrap_or(name).to_string()
+    }
+
+    /// `get` broken down into the terms the formula is made of. The value of each term is the one
+    /// it contributed, so random terms are only drawn once.
+    fn explain(&self) -> Term {
+        Term::new(self.label(), self.get())
+    }
+}
+
+/// A formula or one of its parts with the value it took, see `Formula::explain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Term {
+    pub label: String,
+    pub value: f32,
+    pub terms: Vec<Term>,
+}
+
+impl Term {
+    pub fn new(label: String, value: f32) -> Self {
+        Self {
+            label,
+            value,
+            terms: vec![],
+        }
+    }
+
+    fn fmt_indented(&self, f: &mut Formatter<'_>, indent: usize) -> std::fmt::Result {
+        writeln!(
+            f,
+            "{:indent$}{}: {:.4}",
+            "",
+            self.label,
+            self.value,
+            indent = indent
+        )?;
+        for term in &self.terms {
+            term.fmt_indented(f, indent + 2)?;
+        }
+        Ok(())
+    }
+}
+
+/// One line per term, each indented below the one it is part of.
+impl Display for Term {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        self.fmt_indented(f, 0)
+    }
+}
+
+/// Gives a part of a formula its own name in breakdowns, see `Formula::label`.
+pub struct Labeled {
+    label: String,
+    formula: Arc<Mutex<dyn Formula>>,
+}
+
+impl Labeled {
+    pub fn new(label: &str, formula: Arc<Mutex<dyn Formula>>) -> Self {
+        Self {
+            label: label.to_string(),
+            formula,
+        }
+    }
+}
+
+impl Formula for Labeled {
+    fn get(&self) -> f32 {
+        self.formula.lock().unwrap().get()
+    }
+
+    fn label(&self) -> String {
+        self.label.clone()
+    }
+
+    fn explain(&self) -> Term {
+        Term {
+            label: self.label.clone(),
+            ..self.formula.lock().unwrap().explain()
+        }
+    }
+}
+
+/// Shared formulas are formulas too, so they can be parts of `Sub`, `Div` and the like.
+impl Formula for Arc<Mutex<dyn Formula>> {
+    fn get(&self) -> f32 {
+        self.lock().unwrap().get()
+    }
+
+    fn label(&self) -> String {
+        self.lock().unwrap().label()
+    }
+
+    fn explain(&self) -> Term {
+        self.lock().unwrap().explain()
+    }
 }
 
 pub struct Const {
@@ -18,6 +119,10 @@ impl Formula for Const {
     fn get(&self) -> f32 {
         self.value
     }
+
+    fn label(&self) -> String {
+        self.value.to_string()
+    }
 }
 
 pub struct Add {
@@ -38,6 +143,33 @@ pub struct Div<T: Formula, U: Formula> {
     u: U,
 }
 
+pub struct Neg<T: Formula> {
+    t: T,
+}
+
+pub struct Sqrt<T: Formula> {
+    t: T,
+}
+
+/// Natural logarithm.
+pub struct Ln<T: Formula> {
+    t: T,
+}
+
+/// `t` to the power of `u`.
+pub struct Pow<T: Formula, U: Formula> {
+    t: T,
+    u: U,
+}
+
+pub struct Min {
+    parts: Vec<Arc<Mutex<dyn Formula>>>,
+}
+
+pub struct Max {
+    parts: Vec<Arc<Mutex<dyn Formula>>>,
+}
+
 impl Add {
     pub fn new(parts: Vec<Arc<Mutex<dyn Formula>>>) -> Self {
         Self { parts }
@@ -62,6 +194,58 @@ impl<T: Formula, U: Formula> Div<T, U> {
     }
 }
 
+impl<T: Formula> Neg<T> {
+    pub fn new(t: T) -> Self {
+        Self { t }
+    }
+}
+
+impl<T: Formula> Sqrt<T> {
+    pub fn new(t: T) -> Self {
+        Self { t }
+    }
+}
+
+impl<T: Formula> Ln<T> {
+    pub fn new(t: T) -> Self {
+        Self { t }
+    }
+}
+
+impl<T: Formula, U: Formula> Pow<T, U> {
+    pub fn new(t: T, u: U) -> Self {
+        Self { t, u }
+    }
+}
+
+impl Min {
+    pub fn new(parts: Vec<Arc<Mutex<dyn Formula>>>) -> Self {
+        Self { parts }
+    }
+}
+
+impl Max {
+    pub fn new(parts: Vec<Arc<Mutex<dyn Formula>>>) -> Self {
+        Self { parts }
+    }
+}
+
+/// Terms of every part, with `value` computed from them.
+fn combine(label: &str, terms: Vec<Term>, value: impl FnOnce(&[Term]) -> f32) -> Term {
+    Term {
+        label: label.to_string(),
+        value: value(&terms),
+        terms,
+    }
+}
+
+fn explain_parts(parts: &[Arc<Mutex<dyn Formula>>]) -> Vec<Term> {
+    parts
+        .iter()
+        .map(|part| part.lock().unwrap().explain())
+        .collect()
+}
+
 impl Formula for Add {
     fn get(&self) -> f32 {
         self.parts
@@ -69,12 +253,26 @@ impl Formula for Add {
             .map(|part| part.lock().unwrap().get())
             .sum()
     }
+
+    fn explain(&self) -> Term {
+        combine(&self.label(), explain_parts(&self.parts), |terms| {
+            terms.iter().map(|term| term.value).sum()
+        })
+    }
 }
 
 impl<T: Formula, U: Formula> Formula for Sub<T, U> {
     fn get(&self) -> f32 {
         self.t.get() - self.u.get()
     }
+
+    fn explain(&self) -> Term {
+        combine(
+            &self.label(),
+            vec![self.t.explain(), self.u.explain()],
+            |terms| terms[0].value - terms[1].value,
+        )
+    }
 }
 
 impl Formula for Mul {
@@ -84,10 +282,110 @@ impl Formula for Mul {
             .map(|part| part.lock().unwrap().get())
             .product()
     }
+
+    fn explain(&self) -> Term {
+        combine(&self.label(), explain_parts(&self.parts), |terms| {
+            terms.iter().map(|term| term.value).product()
+        })
+    }
 }
 
 impl<T: Formula, U: Formula> Formula for Div<T, U> {
     fn get(&self) -> f32 {
         self.t.get() / self.u.get()
     }
+
+    fn explain(&self) -> Term {
+        combine(
+            &self.label(),
+            vec![self.t.explain(), self.u.explain()],
+            |terms| terms[0].value / terms[1].value,
+        )
+    }
+}
+
+impl<T: Formula> Formula for Neg<T> {
+    fn get(&self) -> f32 {
+        -self.t.get()
+    }
+
+    fn explain(&self) -> Term {
+        combine(&self.label(), vec![self.t.explain()], |terms| {
+            -terms[0].value
+        })
+    }
+}
+
+impl<T: Formula> Formula for Sqrt<T> {
+    fn get(&self) -> f32 {
+        self.t.get().sqrt()
+    }
+
+    fn explain(&self) -> Term {
+        combine(&self.label(), vec![self.t.explain()], |terms| {
+            terms[0].value.sqrt()
+        })
+    }
+}
+
+impl<T: Formula> Formula for Ln<T> {
+    fn get(&self) -> f32 {
+        self.t.get().ln()
+    }
+
+    fn explain(&self) -> Term {
+        combine(&self.label(), vec![self.t.explain()], |terms| {
+            terms[0].value.ln()
+        })
+    }
+}
+
+impl<T: Formula, U: Formula> Formula for Pow<T, U> {
+    fn get(&self) -> f32 {
+        self.t.get().powf(self.u.get())
+    }
+
+    fn explain(&self) -> Term {
+        combine(
+            &self.label(),
+            vec![self.t.explain(), self.u.explain()],
+            |terms| terms[0].value.powf(terms[1].value),
+        )
+    }
+}
+
+impl Formula for Min {
+    fn get(&self) -> f32 {
+        self.parts
+            .iter()
+            .map(|part| part.lock().unwrap().get())
+            .fold(f32::INFINITY, f32::min)
+    }
+
+    fn explain(&self) -> Term {
+        combine(&self.label(), explain_parts(&self.parts), |terms| {
+            terms
+                .iter()
+                .map(|term| term.value)
+                .fold(f32::INFINITY, f32::min)
+        })
+    }
+}
+
+impl Formula for Max {
+    fn get(&self) -> f32 {
+        self.parts
+            .iter()
+            .map(|part| part.lock().unwrap().get())
+            .fold(f32::NEG_INFINITY, f32::max)
+    }
+
+    fn explain(&self) -> Term {
+        combine(&self.label(), explain_parts(&self.parts), |terms| {
+            terms
+                .iter()
+                .map(|term| term.value)
+                .fold(f32::NEG_INFINITY, f32::max)
+        })
+    }
 }
@@ -0,0 +1,161 @@
+use crate::barracuda::b_core::params::BarracudaUcbParams;
+use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
+
+use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
+use crate::barracuda::b_core::components::static_eval::{win_probability, StaticEval};
+use crate::barracuda::mcts::NodeRef;
+use std::sync::{Arc, Mutex};
+
+pub enum Urgency<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove>
+{
+    /// The same value for every unvisited child.
+    Constant(f32),
+    /// Value of the parent for its player to move, minus a reduction.
+    ParentValue(f32),
+    /// Static evaluation of the child for the player to move at the parent, as a win
+    /// probability, see `StaticEval::evaluate`.
+    StaticEval(Arc<Mutex<dyn StaticEval<Board, Params, Move>>>),
+}
+
+/// UCB of children that were never visited, see `BarracudaAlgorithms::set_first_play_urgency`.
+pub struct FirstPlayUrgency<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+> {
+    urgency: Urgency<Board, Params, Move>,
+    fpu: f32,
+}
+
+impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove>
+    FirstPlayUrgency<Board, Params, Move>
+{
+    pub fn new(urgency: Urgency<Board, Params, Move>) -> Self {
+        Self { urgency, fpu: 0f32 }
+    }
+}
+
+impl<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }>
+    for FirstPlayUrgency<Board, Params, Move>
+{
+    fn set_node(&mut self, state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
+        if state.visits.get() > 0f32 {
+            return;
+        }
+        self.fpu = match &self.urgency {
+            Urgency::Constant(fpu) => *fpu,
+            Urgency::ParentValue(reduction) => {
+                state.parent().map_or(0f32, |parent| parent.eval()) - reduction
+            }
+            Urgency::StaticEval(static_eval) => {
+                let eval = static_eval.lock().unwrap().evaluate(&state.board());
+                win_probability(if state.flipped() { -eval } else { eval })
+            }
+        };
+    }
+
+    fn scores_unvisited(&self) -> bool {
+        true
+    }
+}
+
+impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove> Formula
+    for FirstPlayUrgency<Board, Params, Move>
+{
+    fn get(&self) -> f32 {
+        self.fpu
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::barracuda::b_core::algorithms::ucb::formula::{Add, Const};
+    use crate::barracuda::b_core::algorithms::ucb::fpu::Urgency;
+    use crate::barracuda::b_core::algorithms::ucb::value::Value;
+    use crate::barracuda::b_core::components::policy::Policy;
+    use crate::barracuda::b_core::components::static_eval::StaticEval;
+    use crate::barracuda::b_core::components::ucb::Ucb;
+    use crate::barracuda::b_runner::tests::algorithms;
+    use crate::barracuda::mcts::{BarracudaAlgorithms, Node, NodeRef, Tree, ROOT};
+    use crate::barracuda::traits::{BarracudaAlgorithm, BarracudaBoard};
+    use crate::cf::cf::{CfParams, ConnectFour};
+    use std::sync::{Arc, Mutex};
+
+    /// Always expands towards the first move, so only selection decides which child comes next.
+    struct FirstMove;
+
+    impl BarracudaAlgorithm for FirstMove {}
+
+    impl Policy<ConnectFour, CfParams, usize> for FirstMove {
+        fn pick(&mut self, _: &ConnectFour, _: &[usize]) -> usize {
+            0
+        }
+    }
+
+    /// Scores a position worse for the player to move the further right its pieces are.
+    struct ColumnEval;
+
+    impl BarracudaAlgorithm for ColumnEval {}
+
+    impl StaticEval<ConnectFour, CfParams, usize> for ColumnEval {
+        fn evaluate(&mut self, board: &ConnectFour) -> f32 {
+            let mut eval = 0f32;
+            for (column, pieces) in board.board().iter().enumerate() {
+                eval -= 0.1 * column as f32 * pieces.iter().flatten().count() as f32;
+            }
+            eval
+        }
+    }
+
+    /// Root moves of Connect Four in the order they are first visited.
+    fn first_visits(urgency: Option<Urgency<ConnectFour, CfParams, usize>>) -> Vec<usize> {
+        //Visited children score between -1 and 0, below every win probability
+        let value = Arc::new(Mutex::new(Value::new()));
+        let mut algorithms = BarracudaAlgorithms {
+            ucb_algorithms: vec![value.clone()],
+            ucb: Ucb::new(Arc::new(Mutex::new(Add::new(vec![
+                value.clone(),
+                Const::new(-1.0),
+            ])))),
+            policy: Arc::new(Mutex::new(FirstMove)),
+            ..algorithms()
+        };
+        if let Some(urgency) = urgency {
+            algorithms.set_first_play_urgency(urgency);
+        }
+        let tree = Tree::new(ConnectFour::new(CfParams::default()));
+        let mut order = vec![];
+        for _ in 0..7 {
+            Node::search(&tree, &algorithms, 0.0);
+            let nodes = tree.read();
+            for (mv, child) in NodeRef::new(&nodes, ROOT).children() {
+                if child.visits.get() > 0.0 && !order.contains(&mv) {
+                    order.push(mv);
+                }
+            }
+        }
+        order
+    }
+
+    #[test]
+    fn first_play_urgency_orders_fresh_children() {
+        let in_order = (0..7).collect::<Vec<_>>();
+        assert_eq!(first_visits(None), in_order);
+        assert_eq!(first_visits(Some(Urgency::Constant(1.0))), in_order);
+        assert_eq!(first_visits(Some(Urgency::ParentValue(-1.0))), in_order);
+        //Below any value the first child keeps getting selected
+        assert_eq!(first_visits(Some(Urgency::Constant(-2.0))), vec![0]);
+        assert_eq!(first_visits(Some(Urgency::ParentValue(2.0))), vec![0]);
+        //Pieces further right are worse for the player making the move
+        let column_eval = Arc::new(Mutex::new(ColumnEval));
+        assert_eq!(
+            first_visits(Some(Urgency::StaticEval(column_eval))),
+            vec![0, 6, 5, 4, 3, 2, 1]
+        );
+    }
+}
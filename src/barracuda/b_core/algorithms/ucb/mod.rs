@@ -1,5 +1,8 @@
 pub mod exploration;
+pub mod expression;
 pub mod formula;
+pub mod fpu;
 pub mod p_bias;
+pub mod puct;
 pub mod random;
 pub mod value;
@@ -3,7 +3,7 @@ use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
 
 use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
 use crate::barracuda::b_core::components::static_eval::StaticEval;
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 use std::sync::{Arc, Mutex};
 
 pub struct ProgressiveBias<
@@ -47,13 +47,13 @@ impl<
     > BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }>
     for ProgressiveBias<Board, Params, Move, { DATA_REGISTERS }>
 {
-    fn set_node(&mut self, state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
+    fn set_node(&mut self, state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
         let mut eval = self.static_eval.lock().unwrap();
-        if state.registers[self.flag_register] <= 0.01f32 {
-            state.registers[self.flag_register] = 1f32;
-            state.registers[self.evaluation_register] = eval.evaluate(&state.board)
+        if state.registers[self.flag_register].get() <= 0.01f32 {
+            state.registers[self.flag_register].set(1f32);
+            state.registers[self.evaluation_register].set(eval.evaluate(&state.board()))
         }
-        self.eval = 1f32 - state.registers[self.evaluation_register] / state.visits;
+        self.eval = 1f32 - state.registers[self.evaluation_register].get() / state.visits.get();
     }
 }
 
@@ -0,0 +1,40 @@
+use crate::barracuda::b_core::params::BarracudaUcbParams;
+use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
+
+use crate::barracuda::mcts::NodeRef;
+
+use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
+
+/// Exploration guided by the prior of each move, c * P * sqrt(N) / (1 + n), see `Node::prior`.
+pub struct Puct {
+    exploration: f32,
+    puct: f32,
+}
+
+impl Puct {
+    pub fn new(exploration: f32) -> Self {
+        Self {
+            exploration,
+            puct: 0f32,
+        }
+    }
+}
+
+impl<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }> for Puct
+{
+    fn set_node(&mut self, state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, _: &Move) {
+        self.puct = self.exploration * state.prior() * state.parent_visits().sqrt()
+            / (1f32 + state.visits.get());
+    }
+}
+
+impl Formula for Puct {
+    fn get(&self) -> f32 {
+        self.puct
+    }
+}
@@ -1,7 +1,7 @@
 use crate::barracuda::b_core::params::BarracudaUcbParams;
 use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
 
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 
 use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
 
@@ -22,8 +22,14 @@ impl<
         const DATA_REGISTERS: usize,
     > BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }> for Value
 {
-    fn set_node(&mut self, state: &mut Node<Board, Params, Move, DATA_REGISTERS>, _: &Move) {
-        self.win = 1f32 - state.score / state.visits
+    fn set_node(&mut self, state: NodeRef<Board, Params, Move, DATA_REGISTERS>, _: &Move) {
+        if let (Some(parent), false) = (state.parent(), state.rewards.is_empty()) {
+            let player = parent.board().turn().index();
+            self.win = state.rewards[player].get() / state.visits.get();
+            return;
+        }
+        let win = state.score.get() / state.visits.get();
+        self.win = if state.flipped() { 1f32 - win } else { win }
     }
 }
 
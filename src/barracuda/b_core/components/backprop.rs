@@ -1,4 +1,4 @@
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 use crate::barracuda::traits::{
     BarracudaAlgorithm, BarracudaBoard, BarracudaMove, BarracudaParams,
 };
@@ -10,5 +10,5 @@ pub trait Backprop<
     const DATA_REGISTERS: usize,
 >: BarracudaAlgorithm
 {
-    fn backprop(&mut self, node: &mut Node<Board, Params, Move, { DATA_REGISTERS }>, score: f32);
+    fn backprop(&mut self, node: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, score: f32);
 }
@@ -0,0 +1,22 @@
+use crate::barracuda::traits::{
+    BarracudaAlgorithm, BarracudaBoard, BarracudaMove, BarracudaParams,
+};
+
+/// Value of a position for the player to move, between 0 and 1, with a prior probability for
+/// each of its moves, in the order of `BarracudaBoard::get_moves`, if the evaluator has a policy.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Evaluation {
+    pub value: f32,
+    pub priors: Option<Vec<f32>>,
+}
+
+/// Evaluates many leaves at once, so expensive evaluators can amortize their cost over a batch.
+pub trait BatchEval<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+>: BarracudaAlgorithm
+{
+    /// Returns one evaluation per board, in order.
+    fn evaluate(&mut self, boards: &[Board]) -> Vec<Evaluation>;
+}
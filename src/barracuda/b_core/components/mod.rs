@@ -1,4 +1,5 @@
 pub mod backprop;
+pub mod batch_eval;
 pub mod policy;
 pub mod simulate;
 pub mod static_eval;
@@ -6,4 +6,9 @@ pub trait Policy<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, M
     BarracudaAlgorithm
 {
     fn pick(&mut self, board: &Board, moves: &[Move]) -> usize;
+
+    /// Prior probability of each of `moves`, stored on the children of an expanded node.
+    fn priors(&mut self, _board: &Board, moves: &[Move]) -> Vec<f32> {
+        vec![1f32 / moves.len() as f32; moves.len()]
+    }
 }
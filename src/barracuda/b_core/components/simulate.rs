@@ -9,4 +9,15 @@ pub trait Simulation<
 >: BarracudaAlgorithm
 {
     fn simulate(&mut self, board: &Board) -> f32;
+
+    /// Terminal score, for P1, of the game played out by the last `simulate` call.
+    fn margin(&self) -> Option<f32> {
+        None
+    }
+
+    /// Reward of every player for the game played out by the last `simulate` call, for games
+    /// with more than two players.
+    fn rewards(&self) -> Vec<f32> {
+        vec![]
+    }
 }
@@ -8,5 +8,13 @@ pub trait StaticEval<
     Move: BarracudaMove,
 >: BarracudaAlgorithm
 {
+    /// Score of `board` for the player to move there, positive when they are ahead, negative
+    /// when behind and 0 when even. It is unbounded, so it is flipped for the other player by
+    /// negating it, and turned into a win probability with `win_probability`.
     fn evaluate(&mut self, board: &Board) -> f32;
 }
+
+/// Win probability matching a `StaticEval` score, through the logistic function.
+pub fn win_probability(eval: f32) -> f32 {
+    1f32 / (1f32 + (-eval).exp())
+}
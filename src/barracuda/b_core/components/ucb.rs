@@ -1,18 +1,58 @@
-use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
-use std::sync::{Arc, Mutex};
+use crate::barracuda::b_core::algorithms::ucb::formula::{Formula, Term};
+use std::sync::{Arc, Mutex, MutexGuard};
 
 pub struct Ucb {
     formula: Arc<Mutex<dyn Formula>>,
+    first_play_urgency: Option<Arc<Mutex<dyn Formula>>>,
+    select: Mutex<()>,
 }
 
 impl Ucb {
     pub fn new(formula: Arc<Mutex<dyn Formula>>) -> Self {
-        Self { formula }
+        Self {
+            formula,
+            first_play_urgency: None,
+            select: Mutex::new(()),
+        }
     }
 }
 
 impl Ucb {
+    /// Scores children that were never visited with `first_play_urgency` instead of the formula,
+    /// see `BarracudaAlgorithms::set_first_play_urgency`, which also lets it see the children.
+    pub(crate) fn set_first_play_urgency(&mut self, first_play_urgency: Arc<Mutex<dyn Formula>>) {
+        self.first_play_urgency = Some(first_play_urgency);
+    }
+
     pub fn ucb(&self) -> f32 {
         self.formula.lock().unwrap().get()
     }
+
+    /// UCB of an unvisited child, see `set_first_play_urgency`.
+    pub fn first_play_urgency(&self) -> f32 {
+        match &self.first_play_urgency {
+            Some(first_play_urgency) => first_play_urgency.lock().unwrap().get(),
+            None => f32::INFINITY,
+        }
+    }
+
+    /// `ucb` broken down into the terms of the formula, see `Formula::explain`.
+    pub fn explain(&self) -> Term {
+        self.formula.lock().unwrap().explain()
+    }
+
+    /// `first_play_urgency` broken down like `explain`.
+    pub fn explain_first_play_urgency(&self) -> Term {
+        match &self.first_play_urgency {
+            Some(first_play_urgency) => first_play_urgency.lock().unwrap().explain(),
+            None => Term::new("Unvisited".to_string(), f32::INFINITY),
+        }
+    }
+
+    /// UCB components keep the values of the last `set_node` call, so a worker has to hold this
+    /// lock while it scores a set of children. Workers with their own instance never wait for it,
+    /// see `BarracudaRunner::set_worker_algorithms`.
+    pub fn lock(&self) -> MutexGuard<'_, ()> {
+        self.select.lock().unwrap()
+    }
 }
@@ -1,4 +1,4 @@
-use crate::barracuda::mcts::Node;
+use crate::barracuda::mcts::NodeRef;
 use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
 
 pub trait BarracudaUcbParams<
@@ -10,9 +10,15 @@ pub trait BarracudaUcbParams<
 {
     fn set_node(
         &mut self,
-        state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>,
+        state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>,
         last_move: &Move,
     );
+
+    /// Whether the component scores children that were never visited. Those are left to the
+    /// first play urgency and may not have a board yet, so other components never get them.
+    fn scores_unvisited(&self) -> bool {
+        false
+    }
 }
 
 pub trait BarracudaSimulationParams<
@@ -24,7 +30,7 @@ pub trait BarracudaSimulationParams<
 {
     fn set_node(
         &mut self,
-        state: &mut Node<Board, Params, Move, { DATA_REGISTERS }>,
+        state: NodeRef<Board, Params, Move, { DATA_REGISTERS }>,
         last_move: &Move,
         eval: f32,
     );
@@ -1,9 +1,177 @@
-use crate::barracuda::debugger::Debugger;
-use crate::barracuda::mcts::{BarracudaAlgorithms, Node};
+use crate::barracuda::b_core::algorithms::ucb::formula::Term;
+use crate::barracuda::b_core::components::batch_eval::BatchEval;
+use crate::barracuda::debugger::{Debugger, Eval, PvLine, SearchInfo, TreeStats};
+use crate::barracuda::export::TreeExport;
+use crate::barracuda::mcts::{
+    sample_outcome, BarracudaAlgorithms, BudgetPolicy, Node, NodeBudget, NodeRef,
+    ProgressiveWidening, Proof, Tree, ROOT,
+};
+use crate::barracuda::time_manager::{Clock, TimeManager};
 use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
+use std::io::Write;
 use std::marker::PhantomData;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
-use std::time::Instant;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+/// How many plies below the old root a new position is looked up, enough to cover both sides
+/// moving between two searches.
+const REUSE_DEPTH: usize = 2;
+
+/// Iterations between two checks of the root moves, for `SearchLimits::stable`, for how a
+/// `SearchLimits::clock` is spent and for extending the time of `MoveSelection::MaxRobust`.
+const STABILITY_INTERVAL: u32 = 500;
+
+/// When a search stops, whichever limit it reaches first. Limits left at `None` don't apply, a
+/// search without any only stopping once the root is solved.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct SearchLimits {
+    /// Wall-clock seconds.
+    pub time: Option<f32>,
+    /// Iterations of this search, summed over every worker, each leaf of a batch counting as one.
+    pub iterations: Option<u32>,
+    /// Nodes summed over every tree, see `BarracudaRunner::tree_size`.
+    pub nodes: Option<usize>,
+    /// Longest path from the root, see `BarracudaRunner::depth`.
+    pub depth: Option<usize>,
+    /// Checks in a row, `STABILITY_INTERVAL` iterations apart, finding the same best move.
+    pub stable: Option<u32>,
+    /// Clock of the player to move, split over the game by the `TimeManager` of the runner.
+    pub clock: Option<Clock>,
+}
+
+impl SearchLimits {
+    pub fn time(time: f32) -> Self {
+        Self {
+            time: Some(time),
+            ..Self::default()
+        }
+    }
+
+    pub fn clock(clock: Clock) -> Self {
+        Self {
+            clock: Some(clock),
+            ..Self::default()
+        }
+    }
+}
+
+/// Why a search stopped, see `SearchLimits`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum StopReason {
+    Time,
+    Iterations,
+    Nodes,
+    Depth,
+    Stable,
+    /// The most visited move could no longer be overtaken in the time left, see `TimeManager`.
+    Decided,
+    Solved,
+    /// `BarracudaRunner::stop` was called.
+    Stopped,
+}
+
+/// State of a search while it runs, see `SearchHandle::snapshot`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Snapshot<Move> {
+    pub pv: Vec<Move>,
+    pub eval: Eval,
+    pub visits: u32,
+}
+
+/// Search running on its own thread, see `BarracudaRunner::start`. The runner is handed back
+/// once the search is over.
+pub struct SearchHandle<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    runner: Arc<BarracudaRunner<Board, Params, Move, DATA_REGISTERS>>,
+    thread: JoinHandle<StopReason>,
+}
+
+impl<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > SearchHandle<Board, Params, Move, DATA_REGISTERS>
+{
+    pub fn snapshot(&self) -> Snapshot<Move> {
+        self.runner.snapshot()
+    }
+
+    /// Whether the search reached one of its limits.
+    pub fn finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Stops the search after the iterations running now.
+    pub fn stop(
+        self,
+    ) -> (
+        BarracudaRunner<Board, Params, Move, DATA_REGISTERS>,
+        StopReason,
+    ) {
+        self.runner.stop();
+        self.wait()
+    }
+
+    /// Waits for the search to reach one of its limits.
+    pub fn wait(
+        self,
+    ) -> (
+        BarracudaRunner<Board, Params, Move, DATA_REGISTERS>,
+        StopReason,
+    ) {
+        let reason = self.thread.join().unwrap();
+        match Arc::try_unwrap(self.runner) {
+            Ok(runner) => (runner, reason),
+            Err(_) => unreachable!("the search thread is done with the runner"),
+        }
+    }
+}
+
+/// Limits looking at every root move, which are only checked every `STABILITY_INTERVAL`
+/// iterations.
+struct Checks<Move> {
+    /// Best move found by the last checks of a search with `SearchLimits::stable`.
+    best_move: Option<Move>,
+    stable_checks: u32,
+    next_stability_check: u32,
+    next_time_check: u32,
+}
+
+impl<Move> Default for Checks<Move> {
+    fn default() -> Self {
+        Self {
+            best_move: None,
+            stable_checks: 0,
+            next_stability_check: 0,
+            next_time_check: 0,
+        }
+    }
+}
+
+/// How `best_move` picks a root move. Proven results come first whatever the strategy, and moves
+/// proven to lose are only picked when every move loses.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MoveSelection {
+    /// Highest average score.
+    Max,
+    /// Most visits.
+    Robust,
+    /// Most visits as well as highest average score. Past its time limit the search goes on
+    /// until one move has both, for up to the time limit again, then falls back to `Robust`.
+    MaxRobust,
+    /// Highest lower confidence bound, the average score minus `c / sqrt(visits)`.
+    Secure(f32),
+    /// Sampled with probabilities proportional to `visits^(1 / temperature)`, for diverse
+    /// self-play games. The lower the temperature the closer to `Robust`.
+    Temperature(f32),
+}
 
 pub struct BarracudaRunner<
     Board: BarracudaBoard<Params, Move>,
@@ -11,12 +179,48 @@ pub struct BarracudaRunner<
     Move: BarracudaMove,
     const DATA_REGISTERS: usize,
 > {
-    root: Arc<Mutex<Node<Board, Params, Move, DATA_REGISTERS>>>,
-    algorithms: BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+    trees: Vec<SearchTree<Board, Params, Move, DATA_REGISTERS>>,
     board: Board,
+    threads: usize,
+    virtual_loss: f32,
+    budget: Option<(NodeBudget, BudgetPolicy)>,
+    information_set: bool,
+    transpositions: bool,
+    batch: Option<BatchSettings<Board, Params, Move>>,
+    widening: Option<ProgressiveWidening<Board, Params, Move>>,
+    selection: MoveSelection,
+    multi_pv: usize,
+    time_manager: TimeManager,
+    stop: AtomicBool,
     params: PhantomData<Params>,
 }
 
+type BatchSettings<Board, Params, Move> = (Arc<Mutex<dyn BatchEval<Board, Params, Move>>>, usize);
+
+/// Statistics of a root move, summed over all trees.
+struct RootStats<Move: BarracudaMove> {
+    mv: Move,
+    visits: f32,
+    /// Sum of the scores for the player to move at the root.
+    score: f32,
+    prior: f32,
+    /// Proof of the position after the move, for the player to move at the root.
+    proof: Proof,
+    /// Terminal score the move guarantees.
+    pessimistic: f32,
+}
+
+struct SearchTree<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    tree: Tree<Board, Params, Move, DATA_REGISTERS>,
+    //One per worker, or shared by them if there are fewer, the first is also used for queries
+    algorithms: Vec<BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>>,
+}
+
 impl<
         Board: BarracudaBoard<Params, Move>,
         Params: BarracudaParams,
@@ -30,44 +234,1875 @@ impl<
     ) -> Self {
         let board = Board::new(params);
         Self {
-            root: Arc::new(Mutex::new(Node::new(board.clone(), None))),
-            algorithms,
+            trees: vec![SearchTree {
+                tree: Tree::new(board.clone()),
+                algorithms: vec![algorithms],
+            }],
             board,
-            params: PhantomData::default(),
+            threads: 1,
+            virtual_loss: 1f32,
+            budget: None,
+            information_set: false,
+            transpositions: false,
+            batch: None,
+            widening: None,
+            selection: MoveSelection::Robust,
+            multi_pv: 1,
+            time_manager: TimeManager::default(),
+            stop: AtomicBool::new(false),
+            params: PhantomData,
         }
     }
 
-    pub fn search<D: Debugger<Board, Params, Move>>(&mut self, think_time: f32) {
+    /// Number of workers descending each tree during `search`.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Visits (counted as losses) added to a node while a worker is below it, so concurrent
+    /// workers spread out over the tree. Only used when searching with more than one thread.
+    pub fn set_virtual_loss(&mut self, virtual_loss: f32) {
+        self.virtual_loss = virtual_loss;
+    }
+
+    /// Gives the workers of the main tree their own algorithm instances, the calling thread
+    /// keeping the one the runner was created with. Workers sharing an instance take turns
+    /// scoring children and simulating, since components keep the values of the node they were
+    /// last given, so without this more threads add hardly any playouts. Worker `n` uses instance
+    /// `n` modulo the number of instances. Components shared by the instances, like a
+    /// transposition table, still see every iteration.
+    pub fn set_worker_algorithms(
+        &mut self,
+        algorithms: Vec<BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>>,
+    ) {
+        self.trees[0].algorithms.truncate(1);
+        self.trees[0].algorithms.extend(algorithms);
+    }
+
+    /// Searches an additional independent tree per algorithm instance next to the main one, so
+    /// no algorithm state is shared between them. Root statistics are merged when queried.
+    pub fn set_root_parallel(
+        &mut self,
+        algorithms: Vec<BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>>,
+    ) {
+        self.trees.truncate(1);
+        for algorithms in algorithms {
+            self.trees.push(SearchTree {
+                tree: Tree::new(self.board.clone()),
+                algorithms: vec![algorithms],
+            });
+        }
+        self.apply_settings();
+    }
+
+    /// Limits the size of the search, split evenly between the trees when searching
+    /// root-parallel. `policy` decides what happens once a tree is full.
+    pub fn set_node_budget(&mut self, budget: NodeBudget, policy: BudgetPolicy) {
+        self.budget = Some((budget, policy));
+        self.apply_settings();
+    }
+
+    /// Searches information sets instead of positions, for games with hidden information, see
+    /// `Node::search_information_set`. Nodes move around while such a tree grows, so each tree
+    /// is searched by a single worker, use `set_root_parallel` to search with more threads.
+    pub fn set_information_set_search(&mut self, information_set: bool) {
+        self.information_set = information_set;
+    }
+
+    /// Searches a DAG where transposed positions share a node, see `Tree::set_transpositions`.
+    /// Ignored by information set searches.
+    pub fn set_transpositions(&mut self, transpositions: bool) {
+        self.transpositions = transpositions;
+        self.apply_settings();
+    }
+
+    /// Grows the children each node may select with its visits, see `ProgressiveWidening`.
+    /// Ignored by information set searches.
+    pub fn set_progressive_widening(&mut self, widening: ProgressiveWidening<Board, Params, Move>) {
+        self.widening = Some(widening);
+        self.apply_settings();
+    }
+
+    /// Evaluates leaves with `evaluator` instead of simulating them, `batch_size` at a time, see
+    /// `Node::search_batch`. Each batch spreads out with virtual losses even on a single thread.
+    /// Ignored by information set searches.
+    pub fn set_batch_eval(
+        &mut self,
+        evaluator: Arc<Mutex<dyn BatchEval<Board, Params, Move>>>,
+        batch_size: usize,
+    ) {
+        self.batch = Some((evaluator, batch_size.max(1)));
+    }
+
+    /// Strategy `best_move` picks the move with, `MoveSelection::Robust` by default.
+    pub fn set_move_selection(&mut self, selection: MoveSelection) {
+        self.selection = selection;
+    }
+
+    /// Number of root moves the debugger is shown, see `pv_lines`.
+    pub fn set_multi_pv(&mut self, multi_pv: usize) {
+        self.multi_pv = multi_pv.max(1);
+    }
+
+    /// How searches limited by a `Clock` spend it, see `search_clock`.
+    pub fn set_time_manager(&mut self, time_manager: TimeManager) {
+        self.time_manager = time_manager;
+    }
+
+    /// Searches for `think_time` seconds, see `search_limited`, or `search_clock` to let the
+    /// `TimeManager` decide.
+    pub fn search<D: Debugger<Board, Params, Move> + Default>(
+        &mut self,
+        think_time: f32,
+    ) -> StopReason {
+        self.search_limited::<D>(SearchLimits::time(think_time))
+    }
+
+    /// Searches on the time left on `clock`, split by the `TimeManager` of the runner, see
+    /// `SearchLimits::clock`.
+    pub fn search_clock<D: Debugger<Board, Params, Move> + Default>(
+        &mut self,
+        clock: Clock,
+    ) -> StopReason {
+        self.search_limited::<D>(SearchLimits::clock(clock))
+    }
+
+    /// Searches until one of the `limits` is reached or the root is solved, returning what
+    /// stopped the search. Limits are checked by the calling thread between its iterations, so
+    /// other workers may overshoot them a little.
+    pub fn search_limited<D: Debugger<Board, Params, Move> + Default>(
+        &mut self,
+        limits: SearchLimits,
+    ) -> StopReason {
+        self.search_with(limits, &mut D::default())
+    }
+
+    /// Same as `search_limited`, reporting to `debugger`.
+    pub fn search_with<D: Debugger<Board, Params, Move>>(
+        &mut self,
+        limits: SearchLimits,
+        debugger: &mut D,
+    ) -> StopReason {
+        *self.stop.get_mut() = false;
+        self.run(limits, debugger)
+    }
+
+    /// Searches on a new thread until one of the `limits` is reached, the root is solved or the
+    /// search is stopped through the returned handle, which can be queried in the meantime.
+    pub fn start<D: Debugger<Board, Params, Move> + Default + Send + 'static>(
+        self,
+        limits: SearchLimits,
+    ) -> SearchHandle<Board, Params, Move, DATA_REGISTERS> {
+        self.start_with(limits, D::default())
+    }
+
+    /// Same as `start`, reporting to `debugger`.
+    pub fn start_with<D: Debugger<Board, Params, Move> + Send + 'static>(
+        self,
+        limits: SearchLimits,
+        mut debugger: D,
+    ) -> SearchHandle<Board, Params, Move, DATA_REGISTERS> {
+        self.stop.store(false, Ordering::Relaxed);
+        let runner = Arc::new(self);
+        let searcher = runner.clone();
+        let thread = std::thread::spawn(move || searcher.run(limits, &mut debugger));
+        SearchHandle { runner, thread }
+    }
+
+    /// Searches the position until stopped, on the opponent's time once a move was made. The
+    /// subtree of the opponent's reply is kept by `make_move`.
+    pub fn ponder<D: Debugger<Board, Params, Move> + Default + Send + 'static>(
+        self,
+    ) -> SearchHandle<Board, Params, Move, DATA_REGISTERS> {
+        self.start::<D>(SearchLimits::default())
+    }
+
+    /// Makes the search running on another thread stop after its current iterations, see
+    /// `start`.
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    fn run<D: Debugger<Board, Params, Move>>(
+        &self,
+        limits: SearchLimits,
+        debugger: &mut D,
+    ) -> StopReason {
         let time = Instant::now();
-        let mut debug_counter = D::sampling_rate();
-        while time.elapsed().as_secs_f32() < think_time {
-            Node::search(self.root.clone(), &self.algorithms);
-            if time.elapsed() > debug_counter {
-                let pv = Node::pv(self.root.clone());
-                let root = self.root.lock().unwrap();
-                D::debug(
-                    &self.board,
-                    root.eval(),
-                    root.visits(),
-                    pv.len() as u32,
-                    &pv,
-                );
-                debug_counter = time.elapsed() + D::sampling_rate();
+        let workers = if self.information_set {
+            1
+        } else {
+            self.threads
+        };
+        let virtual_loss = if workers > 1 || self.batch.is_some() {
+            self.virtual_loss
+        } else {
+            0f32
+        };
+        let iterations = AtomicU32::new(0);
+        let stopped = AtomicBool::new(false);
+        let runner = self;
+        let (iterations, stopped) = (&iterations, &stopped);
+        let reason = std::thread::scope(|scope| {
+            for (index, tree) in runner.trees.iter().enumerate() {
+                for worker in 0..workers {
+                    //The calling thread searches the main tree
+                    if index == 0 && worker == 0 {
+                        continue;
+                    }
+                    let algorithms = &tree.algorithms[worker % tree.algorithms.len()];
+                    scope.spawn(move || {
+                        while !stopped.load(Ordering::Relaxed) {
+                            runner.search_tree(tree, algorithms, virtual_loss, iterations);
+                        }
+                    });
+                }
             }
+            let mut checks = Checks::default();
+            debugger.start(&runner.board);
+            let mut debug_counter = debugger.sampling_rate();
+            let reason = loop {
+                let searched = iterations.load(Ordering::Relaxed);
+                if let Some(reason) = runner.stop_reason(&limits, time, searched, &mut checks) {
+                    break reason;
+                }
+                let main = &runner.trees[0];
+                runner.search_tree(main, &main.algorithms[0], virtual_loss, iterations);
+                if time.elapsed() > debug_counter {
+                    let info = runner.info(time.elapsed(), iterations.load(Ordering::Relaxed));
+                    debugger.debug(&runner.board, &info);
+                    debug_counter = time.elapsed().saturating_add(debugger.sampling_rate());
+                }
+            };
+            stopped.store(true, Ordering::Relaxed);
+            reason
+        });
+        //After the workers joined, so the last report has every iteration
+        let info = self.info(time.elapsed(), iterations.load(Ordering::Relaxed));
+        debugger.end(&self.board, &info, reason);
+        reason
+    }
+
+    /// The limit a search started at `time` has reached after `iterations`, if any.
+    fn stop_reason(
+        &self,
+        limits: &SearchLimits,
+        time: Instant,
+        iterations: u32,
+        checks: &mut Checks<Move>,
+    ) -> Option<StopReason> {
+        if self.stop.load(Ordering::Relaxed) {
+            return Some(StopReason::Stopped);
+        }
+        if self.solved() {
+            return Some(StopReason::Solved);
+        }
+        if limits.iterations.is_some_and(|max| iterations >= max) {
+            return Some(StopReason::Iterations);
         }
+        if limits.nodes.is_some_and(|max| self.tree_size() >= max) {
+            return Some(StopReason::Nodes);
+        }
+        if limits.depth.is_some_and(|max| self.depth() >= max) {
+            return Some(StopReason::Depth);
+        }
+        if let Some(stable) = limits.stable {
+            if iterations >= checks.next_stability_check && !self.root_stats().is_empty() {
+                let best_move = self.leading_move();
+                if checks.best_move == Some(best_move) {
+                    checks.stable_checks += 1;
+                } else {
+                    checks.best_move = Some(best_move);
+                    checks.stable_checks = 1;
+                }
+                checks.next_stability_check = iterations + STABILITY_INTERVAL;
+                if checks.stable_checks >= stable {
+                    return Some(StopReason::Stable);
+                }
+            }
+        }
+        let elapsed = time.elapsed().as_secs_f32();
+        let check_root = iterations >= checks.next_time_check;
+        if check_root {
+            checks.next_time_check = iterations + STABILITY_INTERVAL;
+        }
+        if let Some(clock) = &limits.clock {
+            let (target, maximum) = self.time_manager.budget(clock);
+            if elapsed >= maximum {
+                return Some(StopReason::Time);
+            }
+            if check_root && elapsed >= target && !self.unsettled() {
+                return Some(StopReason::Time);
+            }
+            if check_root && self.decided(iterations, elapsed, target) {
+                return Some(StopReason::Decided);
+            }
+        }
+        let think_time = limits.time?;
+        if elapsed < think_time {
+            return None;
+        }
+        if self.selection != MoveSelection::MaxRobust || elapsed >= 2f32 * think_time {
+            return Some(StopReason::Time);
+        }
+        (check_root && !self.unsettled()).then_some(StopReason::Time)
+    }
+
+    /// Whether the most visited and the best scoring root moves differ.
+    fn unsettled(&self) -> bool {
+        !self.root_stats().is_empty()
+            && self.select_move(MoveSelection::Max) != self.select_move(MoveSelection::Robust)
+    }
+
+    /// Whether the most visited root move would stay ahead even if every iteration until
+    /// `deadline`, at the rate of the `iterations` so far, went to the runner-up. A single move
+    /// is decided right away.
+    fn decided(&self, iterations: u32, elapsed: f32, deadline: f32) -> bool {
+        let mut visits = self
+            .root_stats()
+            .iter()
+            .map(|stats| stats.visits)
+            .collect::<Vec<_>>();
+        if visits.is_empty() || iterations == 0 {
+            return false;
+        }
+        if visits.len() == 1 {
+            return true;
+        }
+        visits.sort_by(|a, b| b.total_cmp(a));
+        let left = iterations as f32 / elapsed * (deadline - elapsed).max(0f32);
+        visits[0] - visits[1] > left
     }
 
-    pub fn make_move(&mut self, mv: Move) {
+    /// One iteration on `tree` with `algorithms`, or one batch of them, added to `iterations`.
+    fn search_tree(
+        &self,
+        tree: &SearchTree<Board, Params, Move, DATA_REGISTERS>,
+        algorithms: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        virtual_loss: f32,
+        iterations: &AtomicU32,
+    ) {
+        if self.information_set {
+            iterations.fetch_add(1, Ordering::Relaxed);
+            Node::search_information_set(&tree.tree, algorithms, virtual_loss);
+        } else if let Some((evaluator, batch_size)) = &self.batch {
+            iterations.fetch_add(*batch_size as u32, Ordering::Relaxed);
+            Node::search_batch(&tree.tree, algorithms, evaluator, *batch_size, virtual_loss);
+        } else {
+            iterations.fetch_add(1, Ordering::Relaxed);
+            Node::search(&tree.tree, algorithms, virtual_loss);
+        }
+    }
+
+    /// Proof of the root, taken from whichever tree solved it.
+    pub fn proof(&self) -> Proof {
+        self.trees
+            .iter()
+            .map(|tree| tree.tree.read()[ROOT].proof())
+            .find(|proof| *proof != Proof::Unknown)
+            .unwrap_or(Proof::Unknown)
+    }
+
+    /// Whether the root is solved, in which case searching it further is pointless.
+    pub fn solved(&self) -> bool {
+        self.trees.iter().any(|tree| tree.tree.solved())
+    }
+
+    /// Expected terminal score for the player to move with pessimistic and optimistic bounds,
+    /// if the game reports terminal scores.
+    pub fn margin(&self) -> Option<(f32, f32, f32)> {
+        if !self.trees.iter().any(|tree| tree.tree.scored()) {
+            return None;
+        }
+        let mut margin = 0f32;
+        let mut visits = 0f32;
+        let mut pessimistic = f32::NEG_INFINITY;
+        let mut optimistic = f32::INFINITY;
+        for tree in &self.trees {
+            let nodes = tree.tree.read();
+            margin += nodes[ROOT].margin.get();
+            visits += nodes[ROOT].visits.get();
+            pessimistic = pessimistic.max(nodes[ROOT].pessimistic.get());
+            optimistic = optimistic.min(nodes[ROOT].optimistic.get());
+        }
+        if pessimistic == optimistic {
+            margin = pessimistic * visits;
+        }
+        let margin = if visits > 0f32 { margin / visits } else { 0f32 };
+        Some((margin, pessimistic, optimistic))
+    }
+
+    /// Returns the number of visits kept from the previous search.
+    pub fn make_move(&mut self, mv: Move) -> u32 {
         self.board.make_move(mv);
-        self.root = Arc::new(Mutex::new(Node::new(self.board.clone(), None)));
+        self.reuse_roots()
     }
 
+    /// The move keeping the best score once the score is known exactly, a proven win if there is
+    /// one, otherwise the move picked by the `MoveSelection`.
     pub fn best_move(&self) -> Move {
-        Node::pv(self.root.clone())[0]
+        self.select_move(self.selection)
+    }
+
+    /// `best_move` without sampling, which would show a different move every time.
+    fn leading_move(&self) -> Move {
+        match self.selection {
+            MoveSelection::Temperature(_) => self.select_move(MoveSelection::Robust),
+            selection => self.select_move(selection),
+        }
+    }
+
+    fn select_move(&self, selection: MoveSelection) -> Move {
+        let stats = self.root_stats();
+        if self.solved() && self.margin().is_some() {
+            let mut highest_score = f32::NEG_INFINITY;
+            let mut best_move = None;
+            for stats in &stats {
+                if stats.pessimistic > highest_score || best_move.is_none() {
+                    highest_score = stats.pessimistic;
+                    best_move = Some(stats.mv);
+                }
+            }
+            return best_move.unwrap();
+        }
+        if let Some(win) = stats.iter().find(|stats| stats.proof == Proof::Win) {
+            return win.mv;
+        }
+        let mut candidates = stats
+            .iter()
+            .filter(|stats| stats.proof != Proof::Loss)
+            .collect::<Vec<_>>();
+        if candidates.is_empty() {
+            candidates = stats.iter().collect();
+        }
+        let average = |stats: &RootStats<Move>| {
+            if stats.visits > 0f32 {
+                stats.score / stats.visits
+            } else {
+                f32::NEG_INFINITY
+            }
+        };
+        let key = |stats: &RootStats<Move>| match selection {
+            MoveSelection::Max => average(stats),
+            MoveSelection::Secure(c) => average(stats) - c / stats.visits.sqrt(),
+            _ => stats.visits,
+        };
+        if let MoveSelection::Temperature(temperature) = selection {
+            let most_visits = candidates
+                .iter()
+                .map(|stats| stats.visits)
+                .fold(0f32, f32::max);
+            if temperature > 0f32 && most_visits > 0f32 {
+                //Relative to the most visits, so low temperatures don't overflow
+                let weights = candidates
+                    .iter()
+                    .map(|stats| (stats.visits / most_visits).powf(1f32 / temperature))
+                    .collect::<Vec<_>>();
+                let sum = weights.iter().sum::<f32>();
+                let index = sample_outcome(weights.iter().map(|weight| weight / sum));
+                return candidates[index].mv;
+            }
+        }
+        let mut highest_key = f32::NEG_INFINITY;
+        let mut best_move = None;
+        for stats in candidates {
+            if key(stats) > highest_key || best_move.is_none() {
+                highest_key = key(stats);
+                best_move = Some(stats.mv);
+            }
+        }
+        best_move.unwrap()
+    }
+
+    /// Returns the number of visits kept from the previous search.
+    pub fn set_board(&mut self, board: Board) -> u32 {
+        self.board = board;
+        self.reuse_roots()
+    }
+
+    /// Root visits summed over every tree.
+    pub fn visits(&self) -> u32 {
+        self.trees
+            .iter()
+            .map(|tree| tree.tree.read()[ROOT].visits.get())
+            .sum::<f32>() as u32
+    }
+
+    /// Nodes summed over every tree.
+    pub fn tree_size(&self) -> usize {
+        self.trees.iter().map(|tree| tree.tree.len()).sum()
+    }
+
+    /// Longest path searched in any tree since its root was set.
+    pub fn depth(&self) -> usize {
+        self.trees
+            .iter()
+            .map(|tree| tree.tree.depth())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Average length of the paths searched since the roots were set, over every tree.
+    pub fn average_depth(&self) -> f32 {
+        let depths = self
+            .trees
+            .iter()
+            .map(|tree| tree.tree.average_depth())
+            .collect::<Vec<_>>();
+        depths.iter().sum::<f32>() / depths.len().max(1) as f32
+    }
+
+    /// Estimated bytes used by the nodes of every tree.
+    pub fn memory(&self) -> usize {
+        self.trees.iter().map(|tree| tree.tree.memory()).sum()
+    }
+
+    /// Transpositions merged into an existing node, summed over every tree.
+    pub fn transpositions(&self) -> usize {
+        self.trees
+            .iter()
+            .map(|tree| tree.tree.transpositions())
+            .sum()
+    }
+
+    /// Principal variation, evaluation and visits of the search so far.
+    pub fn snapshot(&self) -> Snapshot<Move> {
+        Snapshot {
+            pv: self.pv(),
+            eval: self.eval(),
+            visits: self.visits(),
+        }
+    }
+
+    fn eval(&self) -> Eval {
+        let mut score = 0f32;
+        let mut visits = 0f32;
+        for tree in &self.trees {
+            let nodes = tree.tree.read();
+            score += nodes[ROOT].score.get();
+            visits += nodes[ROOT].visits.get();
+        }
+        let proof = self.proof();
+        //A search that was just started has no visits yet
+        let pwin = if visits > 0f32 { score / visits } else { 0f32 };
+        Eval {
+            pwin: proof.score().unwrap_or(pwin),
+            proof,
+            margin: self.margin(),
+        }
+    }
+
+    /// State of a search that ran `iterations` in `elapsed`, see `Debugger`.
+    pub fn info(&self, elapsed: Duration, iterations: u32) -> SearchInfo<Move> {
+        let seconds = elapsed.as_secs_f32();
+        SearchInfo {
+            elapsed,
+            iterations,
+            nps: if seconds > 0f32 {
+                iterations as f32 / seconds
+            } else {
+                0f32
+            },
+            visits: self.visits(),
+            max_depth: self.depth(),
+            average_depth: self.average_depth(),
+            eval: self.eval(),
+            pv: self.pv(),
+            children: self.pv_lines(usize::MAX),
+            multi_pv: self.multi_pv,
+            tree: TreeStats {
+                size: self.tree_size(),
+                memory: self.memory(),
+                transpositions: self.transpositions(),
+            },
+        }
+    }
+
+    /// Writes the tree of the first thread, see `TreeExport`.
+    pub fn export_tree<W: Write>(&self, export: &TreeExport, out: &mut W) -> std::io::Result<()> {
+        let tree = &self.trees[0];
+        export.write(&tree.tree, &tree.algorithms[0], out)
+    }
+
+    /// UCB every root move of the first tree is selected with, broken down into the terms of the
+    /// formula, see `Formula::explain`. Unvisited moves get the first play urgency.
+    pub fn ucb_breakdown(&self) -> Vec<(Move, Term)> {
+        let tree = &self.trees[0];
+        let algo = &tree.algorithms[0];
+        let nodes = tree.tree.read();
+        let _ucb_lock = algo.ucb.lock();
+        NodeRef::new(&nodes, ROOT)
+            .children()
+            .map(|(mv, child)| {
+                algo.set_node(child, &mv);
+                let term = if child.visits.get() > 0f32 {
+                    algo.ucb.explain()
+                } else {
+                    algo.ucb.explain_first_play_urgency()
+                };
+                (mv, term)
+            })
+            .collect()
+    }
+
+    /// Prints `ucb_breakdown`, each root move followed by its terms.
+    pub fn print_ucb_breakdown(&self) {
+        for (mv, term) in self.ucb_breakdown() {
+            println!("{}", self.board.move_to_str(mv));
+            print!("{}", term);
+        }
+    }
+
+    /// Best merged root move followed by its line, see `line`.
+    fn pv(&self) -> Vec<Move> {
+        if self.root_stats().is_empty() {
+            return vec![];
+        }
+        self.line(self.leading_move())
+    }
+
+    /// `mv` followed by the pv of the tree that visited it the most.
+    fn line(&self, mv: Move) -> Vec<Move> {
+        let mut pv = vec![mv];
+        let mut highest_visits = -1f32;
+        let mut best_child = vec![];
+        for tree in &self.trees {
+            let nodes = tree.tree.read();
+            for (child_move, child) in NodeRef::new(&nodes, ROOT).children() {
+                if child_move == mv && child.visits.get() > highest_visits {
+                    highest_visits = child.visits.get();
+                    best_child = Node::pv(child);
+                }
+            }
+        }
+        pv.extend(best_child);
+        pv
+    }
+
+    /// The `count` most visited root moves, with the move `best_move` would pick first, each with
+    /// its own pv.
+    pub fn pv_lines(&self, count: usize) -> Vec<PvLine<Move>> {
+        let mut stats = self.root_stats();
+        if stats.is_empty() {
+            return vec![];
+        }
+        let best_move = self.leading_move();
+        stats.sort_by(|a, b| {
+            (b.mv == best_move, b.visits)
+                .partial_cmp(&(a.mv == best_move, a.visits))
+                .unwrap()
+        });
+        stats
+            .iter()
+            .take(count)
+            .map(|stats| PvLine {
+                mv: stats.mv,
+                visits: stats.visits as u32,
+                value: if stats.visits > 0f32 {
+                    stats.score / stats.visits
+                } else {
+                    0f32
+                },
+                prior: stats.prior,
+                proof: stats.proof,
+                pv: self.line(stats.mv),
+            })
+            .collect()
+    }
+
+    /// Visits and scores of every root move, summed over all trees.
+    fn root_stats(&self) -> Vec<RootStats<Move>> {
+        let mut stats: Vec<RootStats<Move>> = vec![];
+        for tree in &self.trees {
+            let nodes = tree.tree.read();
+            for (mv, child) in NodeRef::new(&nodes, ROOT).children() {
+                let (visits, proof) = (child.visits.get(), child.parent_proof());
+                let score = if visits == 0f32 {
+                    0f32
+                } else if !child.rewards.is_empty() {
+                    child.rewards[self.board.turn().index()].get()
+                } else if child.flipped() {
+                    visits - child.score.get()
+                } else {
+                    child.score.get()
+                };
+                let pessimistic = child.parent_bounds().0;
+                if let Some(entry) = stats.iter_mut().find(|stats| stats.mv == mv) {
+                    entry.visits += visits;
+                    entry.score += score;
+                    if entry.proof == Proof::Unknown {
+                        entry.proof = proof;
+                    }
+                    entry.pessimistic = entry.pessimistic.max(pessimistic);
+                } else {
+                    stats.push(RootStats {
+                        mv,
+                        visits,
+                        score,
+                        prior: child.prior(),
+                        proof,
+                        pessimistic,
+                    });
+                }
+            }
+        }
+        stats
+    }
+
+    /// Promotes the subtree of the current board to the root of each tree, or starts a fresh
+    /// tree if the position was never expanded.
+    fn reuse_roots(&mut self) -> u32 {
+        let mut visits = 0;
+        for tree in &mut self.trees {
+            match tree.tree.find(&self.board, REUSE_DEPTH) {
+                Some(node) => {
+                    tree.tree.reroot(node);
+                    visits += tree.tree.read()[ROOT].visits();
+                }
+                None => tree.tree = Tree::new(self.board.clone()),
+            }
+        }
+        self.apply_settings();
+        visits
+    }
+
+    fn apply_settings(&mut self) {
+        let trees = self.trees.len();
+        let budget = self.budget.map(|(budget, policy)| {
+            let budget = match budget {
+                NodeBudget::Nodes(nodes) => NodeBudget::Nodes(nodes / trees),
+                NodeBudget::Bytes(bytes) => NodeBudget::Bytes(bytes / trees),
+            };
+            (budget, policy)
+        });
+        for tree in &mut self.trees {
+            tree.tree.set_budget(budget);
+            tree.tree.set_transpositions(self.transpositions);
+            tree.tree.set_progressive_widening(self.widening.clone());
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::barracuda::b_core::algorithms::policy::softmax::Softmax;
+    use crate::barracuda::b_core::algorithms::policy::uniform::Uniform;
+    use crate::barracuda::b_core::algorithms::simulate::random_playout::RandomPlayout;
+    use crate::barracuda::b_core::algorithms::ucb::exploration::Exploration;
+    use crate::barracuda::b_core::algorithms::ucb::expression::FormulaRegistry;
+    use crate::barracuda::b_core::algorithms::ucb::formula::{Add, Const, Labeled, Mul};
+    use crate::barracuda::b_core::algorithms::ucb::puct::Puct;
+    use crate::barracuda::b_core::algorithms::ucb::value::Value;
+    use crate::barracuda::b_core::components::batch_eval::{BatchEval, Evaluation};
+    use crate::barracuda::b_core::components::simulate::Simulation;
+    use crate::barracuda::b_core::components::ucb::Ucb;
+    use crate::barracuda::b_runner::{BarracudaRunner, MoveSelection, SearchLimits, StopReason};
+    use crate::barracuda::debugger::{JsonDebug, NoDebug};
+    use crate::barracuda::export::{TreeExport, TreeFormat};
+    use crate::barracuda::mcts::{
+        BarracudaAlgorithms, BudgetPolicy, Node, NodeBudget, NodeRef, ProgressiveWidening, Proof,
+        WideningOrder, ROOT,
+    };
+    use crate::barracuda::time_manager::{Clock, TimeManager};
+    use crate::barracuda::traits::{
+        BarracudaAlgorithm, BarracudaBoard, BarracudaMove, BarracudaParams, GameState, Player,
+    };
+    use crate::cf::cf::{CfParams, ConnectFour};
+    use crate::cf::cf_eval::CfEval;
+    use crate::kuhn::{KuhnBoard, KuhnMove, KuhnParams};
+    use crate::pig::{PigBoard, PigMove, PigParams};
+    use crate::tictactoe::ttt::{Square, TicTacToeBoard, TicTacToeParams};
+    use std::collections::HashSet;
+    use std::mem::size_of;
+    use std::sync::{Arc, Mutex};
+    use std::time::{Duration, Instant};
+
+    type TttRunner = BarracudaRunner<TicTacToeBoard, TicTacToeParams, Square, 0>;
+
+    pub(crate) fn algorithms<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+    >() -> BarracudaAlgorithms<Board, Params, Move, 0> {
+        let value = Arc::new(Mutex::new(Value::new()));
+        let exploration = Arc::new(Mutex::new(Exploration::new(1.414)));
+        BarracudaAlgorithms {
+            ucb_algorithms: vec![value.clone(), exploration.clone()],
+            ucb: Ucb::new(Arc::new(Mutex::new(Add::new(vec![value, exploration])))),
+            policy: Arc::new(Mutex::new(Uniform::new())),
+            simulation_algorithms: vec![],
+            simulation: Arc::new(Mutex::new(RandomPlayout::new(
+                usize::MAX,
+                Arc::new(Mutex::new(Uniform::new())),
+            ))),
+            backprop_algorithms: vec![],
+        }
+    }
+
+    fn position(moves: &[(usize, usize)]) -> TicTacToeBoard {
+        let mut board = TicTacToeBoard::new(TicTacToeParams);
+        for &(x, y) in moves {
+            board.make_move(Square::new(x, y));
+        }
+        board
+    }
+
+    fn best_move(board: TicTacToeBoard, threads: usize) -> Square {
+        let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+        runner.set_threads(threads);
+        runner.set_board(board);
+        runner.search::<NoDebug>(0.5);
+        runner.best_move()
+    }
+
+    fn solved_positions() -> Vec<(TicTacToeBoard, Square)> {
+        vec![
+            //X wins on the spot
+            (
+                position(&[(0, 0), (1, 0), (0, 1), (1, 1)]),
+                Square::new(0, 2),
+            ),
+            //X has to block the middle row
+            (
+                position(&[(0, 0), (1, 0), (2, 2), (1, 1)]),
+                Square::new(1, 2),
+            ),
+        ]
+    }
+
+    #[test]
+    fn parallel_search_matches_serial_search() {
+        for (board, solution) in solved_positions() {
+            let serial = best_move(board, 1);
+            let parallel = best_move(board, 4);
+            assert_eq!(serial, solution);
+            assert_eq!(parallel, serial);
+        }
+    }
+
+    /// Random playouts counting the games they play.
+    struct CountingPlayout {
+        playout: RandomPlayout<ConnectFour, CfParams, usize>,
+        simulations: u32,
     }
 
-    pub fn set_board(&mut self, board: Board) {
-        self.board = board.clone();
-        self.root = Arc::new(Mutex::new(Node::new(board, None)));
+    impl BarracudaAlgorithm for CountingPlayout {}
+
+    impl Simulation<ConnectFour, CfParams, usize> for CountingPlayout {
+        fn simulate(&mut self, board: &ConnectFour) -> f32 {
+            self.simulations += 1;
+            self.playout.simulate(board)
+        }
+
+        fn margin(&self) -> Option<f32> {
+            self.playout.margin()
+        }
+    }
+
+    #[test]
+    fn workers_search_with_their_own_algorithms() {
+        for (board, solution) in solved_positions() {
+            let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+            runner.set_threads(4);
+            runner.set_worker_algorithms(vec![algorithms(), algorithms(), algorithms()]);
+            runner.set_board(board);
+            runner.search::<NoDebug>(0.5);
+            assert_eq!(runner.best_move(), solution);
+        }
+
+        //Connect four isn't solved within the search, so every worker gets to simulate
+        let playouts = (0..4)
+            .map(|_| {
+                Arc::new(Mutex::new(CountingPlayout {
+                    playout: RandomPlayout::new(usize::MAX, Arc::new(Mutex::new(Uniform::new()))),
+                    simulations: 0,
+                }))
+            })
+            .collect::<Vec<_>>();
+        let mut instances = playouts
+            .iter()
+            .map(|playout| {
+                let mut algorithms = algorithms();
+                algorithms.simulation = playout.clone();
+                algorithms
+            })
+            .collect::<Vec<_>>();
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            instances.remove(0),
+            CfParams::default(),
+        );
+        runner.set_threads(4);
+        runner.set_worker_algorithms(instances);
+        runner.search::<NoDebug>(0.5);
+        for playout in &playouts {
+            assert!(playout.lock().unwrap().simulations > 0);
+        }
+    }
+
+    /// Workers with their own algorithms only share the tree, so four of them should play at
+    /// least twice as many playouts as one. Run with `cargo test --release -- --ignored` on a
+    /// machine with four cores or more.
+    #[test]
+    #[ignore = "needs four cores"]
+    fn worker_algorithms_scale_with_threads() {
+        let visits = |threads: usize| {
+            let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+                algorithms(),
+                CfParams::default(),
+            );
+            runner.set_threads(threads);
+            runner.set_worker_algorithms((1..threads).map(|_| algorithms()).collect());
+            runner.search::<NoDebug>(1f32);
+            runner.visits()
+        };
+        let (serial, parallel) = (visits(1), visits(4));
+        assert!(
+            parallel >= 2 * serial,
+            "1 thread: {} visits, 4 threads: {} visits",
+            serial,
+            parallel
+        );
+    }
+
+    #[test]
+    fn root_parallel_search_merges_root_statistics() {
+        for (board, solution) in solved_positions() {
+            let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+            runner.set_root_parallel(vec![algorithms(), algorithms()]);
+            runner.set_board(board);
+            runner.search::<NoDebug>(0.5);
+            assert_eq!(runner.best_move(), solution);
+        }
+        //Solved positions stop the search early, so compare visits on an open game
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.set_root_parallel(vec![algorithms(), algorithms()]);
+        runner.search::<NoDebug>(0.2);
+        let main_visits = runner.trees[0].tree.read()[ROOT].visits();
+        assert!(runner.visits() > main_visits);
+    }
+
+    #[test]
+    fn make_move_keeps_searched_subtree() {
+        let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+        assert_eq!(runner.make_move(Square::new(1, 1)), 0);
+        runner.search::<NoDebug>(0.2);
+        let best_move = runner.best_move();
+        let child_visits = {
+            let nodes = runner.trees[0].tree.read();
+            let root = &nodes[ROOT];
+            let index = root.moves.iter().position(|mv| *mv == best_move).unwrap();
+            nodes[root.children.start + index].visits()
+        };
+        assert!(child_visits > 0);
+        assert_eq!(runner.make_move(best_move), child_visits);
+        let nodes = runner.trees[0].tree.read();
+        assert!(nodes[ROOT].parent.is_none());
+        for (index, node) in nodes.iter().enumerate() {
+            for child in node.children.clone() {
+                assert_eq!(nodes[child].parent, Some(index));
+            }
+        }
+    }
+
+    #[test]
+    fn move_selection_strategies() {
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.search::<NoDebug>(0.3);
+        let robust = runner.best_move();
+        runner.set_move_selection(MoveSelection::Max);
+        let max = runner.best_move();
+        runner.set_move_selection(MoveSelection::Secure(0.0));
+        assert_eq!(runner.best_move(), max);
+        runner.set_move_selection(MoveSelection::Temperature(1e-3));
+        assert_eq!(runner.best_move(), robust);
+        runner.set_move_selection(MoveSelection::Temperature(1.0));
+        let sampled = (0..100).map(|_| runner.best_move()).collect::<HashSet<_>>();
+        assert!(sampled.len() > 1);
+
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.set_move_selection(MoveSelection::MaxRobust);
+        let time = Instant::now();
+        runner.search::<NoDebug>(0.2);
+        let agree =
+            runner.select_move(MoveSelection::Max) == runner.select_move(MoveSelection::Robust);
+        assert!(agree || time.elapsed().as_secs_f32() >= 0.4);
+    }
+
+    #[test]
+    fn search_limits_report_what_stopped_the_search() {
+        let cf_runner = || {
+            BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+                algorithms(),
+                CfParams::default(),
+            )
+        };
+        let mut runner = cf_runner();
+        let limits = SearchLimits {
+            iterations: Some(1000),
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            runner.search_limited::<NoDebug>(limits),
+            StopReason::Iterations
+        );
+        assert_eq!(runner.visits(), 1000);
+
+        let mut runner = cf_runner();
+        runner.set_threads(1);
+        let limits = SearchLimits {
+            nodes: Some(500),
+            iterations: Some(1_000_000),
+            ..SearchLimits::default()
+        };
+        assert_eq!(runner.search_limited::<NoDebug>(limits), StopReason::Nodes);
+        //A single thread checks the limit after every iteration, which adds one set of children
+        let children = ConnectFour::new(CfParams::default()).get_moves().len();
+        assert!(runner.tree_size() >= 500 && runner.tree_size() < 500 + children);
+
+        let mut runner = cf_runner();
+        let limits = SearchLimits {
+            depth: Some(4),
+            time: Some(10.0),
+            ..SearchLimits::default()
+        };
+        assert_eq!(runner.search_limited::<NoDebug>(limits), StopReason::Depth);
+        assert_eq!(runner.depth(), 4);
+
+        let mut runner = cf_runner();
+        let limits = SearchLimits {
+            stable: Some(3),
+            time: Some(10.0),
+            ..SearchLimits::default()
+        };
+        assert_eq!(runner.search_limited::<NoDebug>(limits), StopReason::Stable);
+
+        let mut runner = cf_runner();
+        assert_eq!(runner.search::<NoDebug>(0.1), StopReason::Time);
+
+        let (board, _) = solved_positions()[0];
+        let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+        runner.set_board(board);
+        assert_eq!(
+            runner.search_limited::<NoDebug>(SearchLimits::default()),
+            StopReason::Solved
+        );
+    }
+
+    #[test]
+    fn clock_limits_follow_the_time_manager() {
+        let time_manager = TimeManager::new(30, 2.0, 0.0);
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.set_time_manager(time_manager);
+        let time = Instant::now();
+        let reason = runner.search_clock::<NoDebug>(Clock::new(6.0, 0.0, Some(20)));
+        assert!(reason == StopReason::Time || reason == StopReason::Decided);
+        assert!(time.elapsed().as_secs_f32() < 0.7);
+
+        //A single legal move is played without thinking
+        let mut runner =
+            BarracudaRunner::<Stones, StonesParams, usize, 0>::new(algorithms(), StonesParams);
+        runner.set_board(Stones {
+            stones: 1,
+            turn: Player::P1,
+        });
+        let time = Instant::now();
+        let reason = runner.search_clock::<NoDebug>(Clock::new(60.0, 0.0, None));
+        assert_eq!(reason, StopReason::Decided);
+        assert!(time.elapsed().as_secs_f32() < 0.5);
+    }
+
+    #[test]
+    fn pv_lines_report_the_top_root_moves() {
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.search::<NoDebug>(0.3);
+        let lines = runner.pv_lines(3);
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0].mv, runner.best_move());
+        assert_eq!(lines[0].pv, runner.pv());
+        assert!(lines[1].visits >= lines[2].visits);
+        let moves = lines.iter().map(|line| line.mv).collect::<HashSet<_>>();
+        assert_eq!(moves.len(), 3);
+        for line in &lines {
+            assert_eq!(line.pv[0], line.mv);
+            assert!(line.visits > 0 && (0.0..=1.0).contains(&line.value));
+            assert!((line.prior - 1.0 / 7.0).abs() < 1e-4);
+        }
+        assert_eq!(runner.pv_lines(10).len(), 7);
+    }
+
+    #[test]
+    fn json_debug_reports_the_search_as_lines() {
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        let mut debugger = JsonDebug::new(vec![]);
+        let reason = runner.search_with(SearchLimits::time(0.5), &mut debugger);
+        let out = String::from_utf8(debugger.into_inner()).unwrap();
+        let lines = out.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "{\"event\":\"start\"}");
+        assert!(lines.len() > 2);
+        assert!(lines[1..lines.len() - 1]
+            .iter()
+            .all(|line| line.starts_with("{\"event\":\"info\",")));
+        let end = lines[lines.len() - 1];
+        assert!(end.starts_with(&format!("{{\"event\":\"end\",\"reason\":\"{:?}\",", reason)));
+        assert!(end.contains(&format!("\"visits\":{},", runner.visits())));
+        assert_eq!(end.matches("\"move\":").count(), 7);
+        assert!(end.ends_with("]}"));
+
+        let info = runner.info(Duration::from_secs(1), 1000);
+        assert_eq!(info.nps, 1000.0);
+        assert_eq!(info.children.len(), 7);
+        assert_eq!(info.children[0].mv, runner.best_move());
+        assert!(info.max_depth > 0);
+        assert!(info.average_depth > 0.0 && info.average_depth <= info.max_depth as f32);
+    }
+
+    #[test]
+    fn export_tree_cuts_by_depth_and_visits() {
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.search::<NoDebug>(0.3);
+        let export = |format, max_depth, min_visits| {
+            let mut out = vec![];
+            runner
+                .export_tree(&TreeExport::new(format, max_depth, min_visits), &mut out)
+                .unwrap();
+            String::from_utf8(out).unwrap()
+        };
+
+        let json = export(TreeFormat::Json, 1, 1);
+        assert!(json.starts_with(&format!("{{\"move\":null,\"visits\":{},", runner.visits())));
+        assert_eq!(json.matches("\"move\":").count(), 8);
+        assert_eq!(json.matches("\"ucb\":null").count(), 1);
+        //Every root move gets the terms of the formula
+        assert_eq!(json.matches("\"ucb\":{\"label\":\"Add\"").count(), 7);
+        assert_eq!(json.matches("{\"label\":\"Exploration\",").count(), 7);
+        assert!(json.contains(&format!("\"move\":\"{}\"", runner.board.move_to_str(3))));
+        //Only the most visited root moves have enough visits
+        let most_visited = runner.pv_lines(1)[0].visits;
+        let cut = export(TreeFormat::Json, 1, most_visited);
+        assert!(cut.matches("\"move\":").count() < 8);
+        assert_eq!(
+            export(TreeFormat::Json, 0, 1).matches("\"move\":").count(),
+            1
+        );
+
+        let dot = export(TreeFormat::Dot, 2, 1);
+        assert!(dot.starts_with("digraph tree {") && dot.trim_end().ends_with('}'));
+        let nodes = dot.matches("[label=").count();
+        assert_eq!(dot.matches(" -> ").count(), nodes - 1);
+        assert!(nodes > 8);
+        assert!(dot.contains("ucb: "));
+        assert!(dot.contains("\\n  Value: ") && dot.contains("\\n  Exploration: "));
+    }
+
+    #[test]
+    fn ucb_breakdown_explains_every_root_move() {
+        let value = Arc::new(Mutex::new(Value::new()));
+        let exploration = Arc::new(Mutex::new(Exploration::new(1.0)));
+        let bonus = Labeled::new(
+            "bonus",
+            Arc::new(Mutex::new(Mul::new(vec![
+                Const::new(0.5),
+                exploration.clone(),
+            ]))),
+        );
+        let mut algorithms = algorithms::<ConnectFour, CfParams, usize>();
+        algorithms.ucb_algorithms = vec![value.clone(), exploration];
+        algorithms.ucb = Ucb::new(Arc::new(Mutex::new(Add::new(vec![
+            value,
+            Arc::new(Mutex::new(bonus)),
+        ]))));
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms,
+            CfParams::default(),
+        );
+        runner.search::<NoDebug>(0.2);
+
+        let breakdown = runner.ucb_breakdown();
+        assert_eq!(breakdown.len(), 7);
+        for (_, term) in &breakdown {
+            assert_eq!(term.label, "Add");
+            let labels = term
+                .terms
+                .iter()
+                .map(|term| &term.label)
+                .collect::<Vec<_>>();
+            assert_eq!(labels, ["Value", "bonus"]);
+            let bonus = &term.terms[1];
+            assert_eq!(bonus.terms[0].label, "0.5");
+            assert_eq!(bonus.terms[1].label, "Exploration");
+            assert!((bonus.value - 0.5 * bonus.terms[1].value).abs() < 1e-6);
+            assert!((term.value - term.terms[0].value - bonus.value).abs() < 1e-6);
+            assert!(term.value > 0.0 && term.value.is_finite());
+        }
+        let printed = breakdown[0].1.to_string();
+        assert!(printed.starts_with("Add: "));
+        assert!(printed.contains("\n  bonus: ") && printed.contains("\n    Exploration: "));
+    }
+
+    #[test]
+    fn parsed_formulas_drive_the_search() {
+        let mut registry = FormulaRegistry::new();
+        registry.register("value", Arc::new(Mutex::new(Value::new())));
+        registry.register("explore", Arc::new(Mutex::new(Exploration::new(1.0))));
+        let expression = registry
+            .parse("value + 1.41 * explore + 0 * value")
+            .unwrap();
+        let mut algorithms = algorithms::<ConnectFour, CfParams, usize>();
+        algorithms.ucb_algorithms = expression.ucb_algorithms;
+        algorithms.ucb = Ucb::new(expression.formula);
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms,
+            CfParams::default(),
+        );
+        runner.search::<NoDebug>(0.2);
+        assert!(runner.visits() > 100);
+        for (_, term) in runner.ucb_breakdown() {
+            let labels = term
+                .terms
+                .iter()
+                .map(|term| &term.label)
+                .collect::<Vec<_>>();
+            assert_eq!(labels, ["Value", "Mul", "Mul"]);
+            assert_eq!(term.terms[1].terms[1].label, "Exploration");
+        }
+    }
+
+    #[test]
+    fn search_handle_can_be_queried_and_stopped() {
+        let runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        //Queried before the first iteration, the root has no visits to average
+        let snapshot = runner.snapshot();
+        assert_eq!((snapshot.visits, snapshot.eval.pwin), (0, 0.0));
+        let time = Instant::now();
+        let search = runner.start::<NoDebug>(SearchLimits::time(10.0));
+        std::thread::sleep(Duration::from_millis(200));
+        let snapshot = search.snapshot();
+        assert!(snapshot.visits > 0 && !snapshot.pv.is_empty());
+        assert!(!search.finished());
+        let (mut runner, reason) = search.stop();
+        assert_eq!(reason, StopReason::Stopped);
+        assert!(time.elapsed().as_secs_f32() < 5.0);
+        assert!(runner.visits() >= snapshot.visits);
+
+        //Pondering on the opponent's time keeps the subtree of their reply
+        let best_move = runner.best_move();
+        runner.make_move(best_move);
+        let search = runner.ponder::<NoDebug>();
+        std::thread::sleep(Duration::from_millis(200));
+        let (mut runner, reason) = search.stop();
+        assert_eq!(reason, StopReason::Stopped);
+        let reply = runner.best_move();
+        assert!(runner.make_move(reply) > 0);
+    }
+
+    #[test]
+    fn solver_stops_once_root_is_proven() {
+        let positions = [
+            (position(&[(0, 0), (1, 0), (0, 1), (1, 1)]), Proof::Win),
+            (position(&[(1, 1), (0, 0)]), Proof::Draw),
+            //O can't stop both of X's threats
+            (
+                position(&[(0, 0), (1, 0), (1, 1), (2, 2), (0, 2)]),
+                Proof::Loss,
+            ),
+        ];
+        for &(board, proof) in positions.iter() {
+            let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+            runner.set_board(board);
+            let time = Instant::now();
+            runner.search::<NoDebug>(10.0);
+            assert!(time.elapsed().as_secs_f32() < 10.0);
+            assert_eq!(runner.proof(), proof);
+        }
+        let (board, solution) = solved_positions()[0];
+        let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+        runner.set_board(board);
+        runner.search::<NoDebug>(10.0);
+        assert_eq!(runner.best_move(), solution);
+    }
+
+    #[derive(Default)]
+    struct CoinsParams;
+
+    impl BarracudaParams for CoinsParams {}
+
+    /// Players take turns taking a coin from either end of the row, the terminal score is the
+    /// difference between the values taken.
+    #[derive(Clone, Hash, PartialEq, Eq)]
+    struct Coins {
+        coins: [i32; 6],
+        left: usize,
+        right: usize,
+        margin: i32,
+        turn: Player,
+    }
+
+    impl Coins {
+        /// Best margin for the player to move on `coins[left..right]` and the move reaching it.
+        fn solve(&self, left: usize, right: usize) -> (i32, usize) {
+            if left == right {
+                return (0, 0);
+            }
+            let take_left = self.coins[left] - self.solve(left + 1, right).0;
+            let take_right = self.coins[right - 1] - self.solve(left, right - 1).0;
+            if take_left >= take_right {
+                (take_left, 0)
+            } else {
+                (take_right, 1)
+            }
+        }
+    }
+
+    impl BarracudaBoard<CoinsParams, usize> for Coins {
+        fn new(_: CoinsParams) -> Self {
+            Self {
+                coins: [3, 9, 1, 2, 7, 4],
+                left: 0,
+                right: 6,
+                margin: 0,
+                turn: Player::P1,
+            }
+        }
+
+        fn make_move(&mut self, mv: usize) {
+            let coin = if mv == 0 {
+                self.left += 1;
+                self.coins[self.left - 1]
+            } else {
+                self.right -= 1;
+                self.coins[self.right]
+            };
+            self.margin += match self.turn {
+                Player::P1 => coin,
+                _ => -coin,
+            };
+            self.turn = self.turn.next(2);
+        }
+
+        fn get_moves(&self) -> Vec<usize> {
+            if self.left == self.right {
+                vec![]
+            } else {
+                vec![0, 1]
+            }
+        }
+
+        fn game_state(&self) -> GameState {
+            if self.left < self.right {
+                GameState::Ongoing
+            } else if self.margin > 0 {
+                GameState::End(Some(Player::P1))
+            } else if self.margin < 0 {
+                GameState::End(Some(Player::P2))
+            } else {
+                GameState::End(None)
+            }
+        }
+
+        fn turn(&self) -> Player {
+            self.turn
+        }
+
+        fn move_to_str(&self, mv: usize) -> String {
+            String::from(if mv == 0 { "left" } else { "right" })
+        }
+
+        fn terminal_score(&self) -> Option<f32> {
+            Some(self.margin as f32).filter(|_| self.left == self.right)
+        }
+    }
+
+    #[test]
+    fn score_bounds_find_the_best_margin() {
+        let board = Coins::new(CoinsParams);
+        let (margin, solution) = board.solve(board.left, board.right);
+        let mut runner =
+            BarracudaRunner::<Coins, CoinsParams, usize, 0>::new(algorithms(), CoinsParams);
+        let time = Instant::now();
+        runner.search::<NoDebug>(10.0);
+        assert!(time.elapsed().as_secs_f32() < 10.0);
+        let (expected, pessimistic, optimistic) = runner.margin().unwrap();
+        assert_eq!(expected, margin as f32);
+        assert_eq!((pessimistic, optimistic), (margin as f32, margin as f32));
+        assert_eq!(runner.best_move(), solution);
+    }
+
+    fn pig_position(moves: &[PigMove]) -> PigBoard {
+        let mut board = PigBoard::new(PigParams::new(10));
+        for &mv in moves {
+            board.make_move(mv);
+        }
+        board
+    }
+
+    #[test]
+    fn chance_nodes_back_up_expectations() {
+        use PigMove::{Die, Hold, Roll};
+        //P1 has 8 of 10 points, any roll but a one wins after holding
+        let board = pig_position(&[Roll, Die(4), Roll, Die(4), Hold, Hold]);
+        let mut runner = BarracudaRunner::<PigBoard, PigParams, PigMove, 0>::new(
+            algorithms(),
+            PigParams::new(10),
+        );
+        runner.set_board(board);
+        runner.search::<NoDebug>(0.5);
+        assert_eq!(runner.proof(), Proof::Unknown);
+        assert_eq!(runner.best_move(), Roll);
+        let nodes = runner.trees[0].tree.read();
+        let (_, roll) = NodeRef::new(&nodes, ROOT)
+            .children()
+            .find(|(mv, _)| *mv == Roll)
+            .unwrap();
+        assert!(roll.chance);
+        //Unlike a decision node, one winning outcome does not prove the roll
+        assert_eq!(roll.parent_proof(), Proof::Unknown);
+        let mut won = 0;
+        for (mv, outcome) in roll.children() {
+            if outcome.parent_proof() == Proof::Win {
+                won += 1;
+            }
+            let share = outcome.visits.get() / roll.visits.get();
+            assert!((0.1..0.25).contains(&share), "{:?} got {}", mv, share);
+        }
+        assert_eq!(won, 5);
+        assert!(roll.eval() > 0.75);
+    }
+
+    #[test]
+    fn solver_proves_wins_in_stochastic_games() {
+        use PigMove::{Die, Hold, Roll};
+        let board = pig_position(&[Roll, Die(4), Roll, Die(4), Hold, Hold, Roll, Die(2)]);
+        let mut runner = BarracudaRunner::<PigBoard, PigParams, PigMove, 0>::new(
+            algorithms(),
+            PigParams::new(10),
+        );
+        runner.set_board(board);
+        runner.search::<NoDebug>(10.0);
+        assert_eq!(runner.proof(), Proof::Win);
+        assert_eq!(runner.best_move(), Hold);
+    }
+
+    type KuhnRunner = BarracudaRunner<KuhnBoard, KuhnParams, KuhnMove, 0>;
+
+    fn kuhn_position(moves: &[KuhnMove]) -> KuhnBoard {
+        let mut board = KuhnBoard::new(KuhnParams);
+        for &mv in moves {
+            board.make_move(mv);
+        }
+        board
+    }
+
+    #[test]
+    fn information_set_search_hides_the_opponents_card() {
+        use KuhnMove::{Bet, Call, Deal, Fold};
+        //P2 holds the queen facing a bet from P1's king
+        let board = kuhn_position(&[Deal(2, 1), Bet]);
+        let mut runner = KuhnRunner::new(algorithms(), KuhnParams);
+        runner.set_board(board);
+        runner.search::<NoDebug>(0.3);
+        //Seeing the king, calling only loses more
+        assert_eq!(runner.best_move(), Fold);
+
+        let mut runner = KuhnRunner::new(algorithms(), KuhnParams);
+        runner.set_information_set_search(true);
+        runner.set_board(board);
+        runner.search::<NoDebug>(0.3);
+        assert_eq!(runner.proof(), Proof::Unknown);
+        assert_eq!(runner.best_move(), Call);
+        let nodes = runner.trees[0].tree.read();
+        let root = NodeRef::new(&nodes, ROOT);
+        let (_, call) = root.children().find(|(mv, _)| *mv == Call).unwrap();
+        //The queen beats the jack half of the time
+        assert!((0.4..0.6).contains(&call.eval()));
+        for (_, child) in root.children() {
+            assert!((child.availability.get() - root.visits.get()).abs() < 1.0);
+        }
+    }
+
+    #[test]
+    fn information_set_search_adds_moves_as_they_show_up() {
+        let mut runner = KuhnRunner::new(algorithms(), KuhnParams);
+        runner.set_information_set_search(true);
+        runner.search::<NoDebug>(0.3);
+        let nodes = runner.trees[0].tree.read();
+        let root = NodeRef::new(&nodes, ROOT);
+        //Every deal is only available in the iterations sampling it
+        assert_eq!(root.moves.len(), 6);
+        for (_, deal) in root.children() {
+            assert!((deal.availability.get() - deal.visits.get()).abs() < 1.0);
+            let share = deal.visits.get() / root.visits.get();
+            assert!((0.1..0.25).contains(&share));
+        }
+        let mut reachable = vec![ROOT];
+        let mut index = 0;
+        while index < reachable.len() {
+            let node = reachable[index];
+            for child in nodes[node].children.clone() {
+                assert_eq!(nodes[child].parent, Some(node));
+                reachable.push(child);
+            }
+            index += 1;
+        }
+    }
+
+    #[derive(Default)]
+    struct StonesParams;
+
+    impl BarracudaParams for StonesParams {}
+
+    /// Three players take turns taking one or two stones, whoever takes the last stone wins.
+    #[derive(Clone, Hash, PartialEq, Eq)]
+    struct Stones {
+        stones: usize,
+        turn: Player,
+    }
+
+    impl BarracudaBoard<StonesParams, usize> for Stones {
+        fn new(_: StonesParams) -> Self {
+            Self {
+                stones: 4,
+                turn: Player::P1,
+            }
+        }
+
+        fn make_move(&mut self, mv: usize) {
+            self.stones -= mv;
+            self.turn = self.turn.next(3);
+        }
+
+        fn get_moves(&self) -> Vec<usize> {
+            (1..=self.stones.min(2)).collect()
+        }
+
+        fn game_state(&self) -> GameState {
+            if self.stones > 0 {
+                GameState::Ongoing
+            } else {
+                GameState::End(Some(self.turn.next(3).next(3)))
+            }
+        }
+
+        fn turn(&self) -> Player {
+            self.turn
+        }
+
+        fn players(&self) -> usize {
+            3
+        }
+
+        fn move_to_str(&self, mv: usize) -> String {
+            mv.to_string()
+        }
+    }
+
+    #[test]
+    fn rewards_are_backed_up_for_every_player() {
+        let mut runner =
+            BarracudaRunner::<Stones, StonesParams, usize, 0>::new(algorithms(), StonesParams);
+        runner.search::<NoDebug>(0.3);
+        //Taking two leaves P2 a winning two, taking one leaves P2 nothing but losing moves
+        assert_eq!(runner.best_move(), 1);
+        assert_eq!(runner.proof(), Proof::Unknown);
+        let nodes = runner.trees[0].tree.read();
+        let visited = (0..nodes.len())
+            .map(|id| NodeRef::new(&nodes, id))
+            .filter(|node| node.visits.get() > 0.0);
+        for node in visited {
+            let rewards: f32 = node.rewards.iter().map(|reward| reward.get()).sum();
+            assert!((rewards - node.visits.get()).abs() < 1.0);
+            let own = node.rewards[node.board().turn().index()].get();
+            assert!((own - node.score.get()).abs() < 1e-3);
+        }
+        let root = NodeRef::new(&nodes, ROOT);
+        let (_, take_one) = root.children().find(|(mv, _)| *mv == 1).unwrap();
+        assert!(take_one.rewards[Player::P2.index()].get() < 1.0);
+    }
+
+    #[test]
+    fn transpositions_share_a_node() {
+        for (board, solution) in solved_positions() {
+            let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+            runner.set_transpositions(true);
+            runner.set_board(board);
+            runner.search::<NoDebug>(10.0);
+            assert_ne!(runner.proof(), Proof::Unknown);
+            assert_eq!(runner.best_move(), solution);
+        }
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.set_transpositions(true);
+        runner.search::<NoDebug>(0.3);
+        assert!(runner.transpositions() > 0);
+        runner.make_move(runner.best_move());
+        let nodes = runner.trees[0].tree.read();
+        let mut positions = HashSet::new();
+        for node in (0..nodes.len()).map(|id| NodeRef::new(&nodes, id)) {
+            match node.transposition {
+                Some(shared) => {
+                    assert!(NodeRef::new(&nodes, shared).board() == node.board());
+                    assert!(nodes[shared].transposition.is_none());
+                    //Links never collect statistics of their own
+                    assert!(node.visits.get() < 1.0 && node.moves.is_empty());
+                }
+                None => assert!(positions.insert(node.board().clone())),
+            }
+        }
+    }
+
+    /// Even evaluator preferring central columns, recording the size of every batch.
+    struct CountingEval {
+        batches: Vec<usize>,
+    }
+
+    impl BarracudaAlgorithm for CountingEval {}
+
+    impl BatchEval<ConnectFour, CfParams, usize> for CountingEval {
+        fn evaluate(&mut self, boards: &[ConnectFour]) -> Vec<Evaluation> {
+            self.batches.push(boards.len());
+            boards
+                .iter()
+                .map(|board| {
+                    let moves = board.get_moves();
+                    let weights = moves
+                        .iter()
+                        .map(|&column| 1f32 / (1f32 + (column as f32 - 3f32).powi(2)))
+                        .collect::<Vec<_>>();
+                    let total = weights.iter().sum::<f32>();
+                    Evaluation {
+                        value: 0.5,
+                        priors: Some(weights.iter().map(|weight| weight / total).collect()),
+                    }
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn batched_evaluation_collects_leaves() {
+        let evaluator = Arc::new(Mutex::new(CountingEval { batches: vec![] }));
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.set_batch_eval(evaluator.clone(), 8);
+        runner.search::<NoDebug>(0.3);
+        let batches = evaluator.lock().unwrap().batches.clone();
+        //The root's policy comes first
+        assert_eq!(batches[0], 1);
+        assert!(batches.len() > 1 && batches[1..].iter().all(|&batch| batch == 8));
+        assert_eq!(runner.visits() as usize, batches.iter().sum::<usize>() - 1);
+        let nodes = runner.trees[0].tree.read();
+        let root = NodeRef::new(&nodes, ROOT);
+        let priors = root.children().map(|(_, child)| child.prior()).sum::<f32>();
+        assert!((priors - 1.0).abs() < 1e-3);
+        let (center, _) = root
+            .children()
+            .max_by(|(_, a), (_, b)| a.prior().total_cmp(&b.prior()))
+            .unwrap();
+        assert_eq!(center, 3);
+        //Every virtual loss was taken back
+        for node in nodes.iter().filter(|node| node.visits.get() > 0.5) {
+            assert!((node.score.get() - node.visits.get() * 0.5).abs() < 1e-2 * node.visits.get());
+        }
+    }
+
+    #[test]
+    fn puct_follows_policy_priors() {
+        //Without a value term PUCT alone picks the children, visiting them in proportion to
+        //their priors
+        let puct = Arc::new(Mutex::new(Puct::new(1.5)));
+        let algorithms = BarracudaAlgorithms {
+            ucb_algorithms: vec![puct.clone()],
+            ucb: Ucb::new(puct),
+            policy: Arc::new(Mutex::new(Softmax::new(Arc::new(Mutex::new(CfEval {}))))),
+            ..algorithms()
+        };
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms,
+            CfParams::default(),
+        );
+        let limits = SearchLimits {
+            iterations: Some(3000),
+            ..SearchLimits::default()
+        };
+        assert_eq!(
+            runner.search_limited::<NoDebug>(limits),
+            StopReason::Iterations
+        );
+        let nodes = runner.trees[0].tree.read();
+        let root = NodeRef::new(&nodes, ROOT);
+        let children = root
+            .children()
+            .map(|(mv, child)| (mv, child.prior(), child.visits.get()))
+            .collect::<Vec<_>>();
+        assert!((children.iter().map(|(_, prior, _)| prior).sum::<f32>() - 1.0).abs() < 1e-3);
+        //The evaluation favours the center, for the player making the move
+        let (center, prior, _) = children[3];
+        assert_eq!(center, 3);
+        assert!(children.iter().all(|(_, other, _)| *other <= prior));
+        assert!(children[0].1 < prior);
+        let visits = children.iter().map(|(_, _, visits)| visits).sum::<f32>();
+        for (mv, prior, child_visits) in &children {
+            let share = child_visits / visits;
+            assert!(
+                (share - prior).abs() < 0.02,
+                "move {} has prior {} and visit share {}",
+                mv,
+                prior,
+                share
+            );
+        }
+    }
+
+    #[test]
+    fn progressive_widening_creates_children_lazily() {
+        for (board, solution) in solved_positions() {
+            let mut runner = TttRunner::new(algorithms(), TicTacToeParams);
+            runner.set_progressive_widening(ProgressiveWidening::new(
+                1.0,
+                0.5,
+                WideningOrder::Prior,
+            ));
+            runner.set_board(board);
+            runner.search::<NoDebug>(10.0);
+            assert_ne!(runner.proof(), Proof::Unknown);
+            assert_eq!(runner.best_move(), solution);
+        }
+        let widening = ProgressiveWidening::new(
+            1.0,
+            0.5,
+            WideningOrder::StaticEval(Arc::new(Mutex::new(CfEval {}))),
+        );
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.set_progressive_widening(widening);
+        runner.search::<NoDebug>(0.3);
+        //Without widening every iteration adds seven children
+        assert!(runner.tree_size() < 4 * runner.visits() as usize);
+        let nodes = runner.trees[0].tree.read();
+        //The center columns are the best for the evaluation
+        let mut first = nodes[ROOT].moves[..2].to_vec();
+        first.sort();
+        assert_eq!(first, vec![3, 4]);
+        let mut widened = false;
+        for id in 0..nodes.len() {
+            let node = NodeRef::new(&nodes, id);
+            if node.transposition.is_some() || node.moves.is_empty() {
+                continue;
+            }
+            widened |= node.children.len() < node.moves.len();
+            let width = (node.visits.get().max(1.0).sqrt().ceil()) as usize;
+            let visited = node
+                .children()
+                .filter(|(_, child)| child.visits.get() > 0.0)
+                .filter(|(_, child)| child.parent_proof() != Proof::Loss)
+                .count();
+            assert!(
+                visited <= width,
+                "{} children visited of {}",
+                visited,
+                width
+            );
+        }
+        assert!(widened);
+    }
+
+    #[test]
+    fn node_budget_bounds_tree_size() {
+        let budgets = [NodeBudget::Nodes(300), NodeBudget::Bytes(100_000)];
+        let policies = [BudgetPolicy::StopExpanding, BudgetPolicy::Prune];
+        for &budget in budgets.iter() {
+            for &policy in policies.iter() {
+                let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+                    algorithms(),
+                    CfParams::default(),
+                );
+                runner.set_node_budget(budget, policy);
+                runner.search::<NoDebug>(0.3);
+                match budget {
+                    NodeBudget::Nodes(max_nodes) => assert!(runner.tree_size() <= max_nodes),
+                    NodeBudget::Bytes(max_bytes) => assert!(runner.memory() <= max_bytes),
+                }
+                //Search keeps going once the tree is full
+                assert!(runner.visits() as usize > runner.tree_size());
+            }
+        }
+    }
+
+    #[test]
+    fn lazy_boards_take_less_memory_per_node() {
+        let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+            algorithms(),
+            CfParams::default(),
+        );
+        runner.search_limited::<NoDebug>(SearchLimits {
+            iterations: Some(3000),
+            ..SearchLimits::default()
+        });
+        //Every node would take at least this much if all of them kept a board
+        let board = ConnectFour::new(CfParams::default()).heap_size();
+        let eager = size_of::<Node<ConnectFour, CfParams, usize, 0>>() + board;
+        let per_node = runner.memory() / runner.tree_size();
+        assert!(per_node < eager);
+    }
+
+    /// Run with `cargo test --release -- --ignored --nocapture` to print playouts per second,
+    /// with full random playouts and with the tree alone. Each worker gets its own algorithms.
+    #[test]
+    #[ignore]
+    fn bench_connect_four() {
+        for playout_depth in [usize::MAX, 0] {
+            for threads in [1, 4] {
+                let algorithms = || {
+                    let mut algorithms = algorithms();
+                    algorithms.simulation = Arc::new(Mutex::new(RandomPlayout::new(
+                        playout_depth,
+                        Arc::new(Mutex::new(Uniform::new())),
+                    )));
+                    algorithms
+                };
+                let mut runner = BarracudaRunner::<ConnectFour, CfParams, usize, 0>::new(
+                    algorithms(),
+                    CfParams::default(),
+                );
+                runner.set_threads(threads);
+                runner.set_worker_algorithms((1..threads).map(|_| algorithms()).collect());
+                let time = Instant::now();
+                runner.search::<NoDebug>(1f32);
+                let nps = runner.visits() as f32 / time.elapsed().as_secs_f32();
+                println!(
+                    "playout depth: {} threads: {} nps: {}",
+                    playout_depth, threads, nps as u32
+                );
+            }
+        }
     }
 }
@@ -1,49 +1,291 @@
+use crate::barracuda::b_runner::StopReason;
+use crate::barracuda::mcts::Proof;
 use crate::barracuda::traits::BarracudaBoard;
+use std::io::{Stdout, Write};
 use std::time::Duration;
 
+/// Evaluation of the root for the player to move.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Eval {
+    pub pwin: f32,
+    pub proof: Proof,
+    /// Expected terminal score and its pessimistic and optimistic bounds, for games reporting
+    /// terminal scores.
+    pub margin: Option<(f32, f32, f32)>,
+}
+
+/// Size of the search trees.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TreeStats {
+    pub size: usize,
+    /// Estimated bytes used by the nodes.
+    pub memory: usize,
+    /// Positions merged into an existing node when searching a DAG.
+    pub transpositions: usize,
+}
+
+/// A root move with its statistics summed over all trees, see `BarracudaRunner::pv_lines`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PvLine<Move> {
+    pub mv: Move,
+    pub visits: u32,
+    /// Average score for the player to move at the root.
+    pub value: f32,
+    pub prior: f32,
+    /// Proof of the move for the player to move at the root.
+    pub proof: Proof,
+    /// Principal variation starting with the move.
+    pub pv: Vec<Move>,
+}
+
+/// State of a search handed to a `Debugger`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchInfo<Move> {
+    pub elapsed: Duration,
+    /// Iterations of this search, see `SearchLimits::iterations`.
+    pub iterations: u32,
+    /// Iterations per second.
+    pub nps: f32,
+    /// Root visits, including those kept from previous searches.
+    pub visits: u32,
+    /// Longest and average length of the paths selected since the root was set.
+    pub max_depth: usize,
+    pub average_depth: f32,
+    pub eval: Eval,
+    pub pv: Vec<Move>,
+    /// Every root move, the one `best_move` picks first followed by the most visited.
+    pub children: Vec<PvLine<Move>>,
+    /// How many of the `children` were asked for as lines, see `BarracudaRunner::set_multi_pv`.
+    pub multi_pv: usize,
+    pub tree: TreeStats,
+}
+
+/// Reports on a search while it runs. `debug` is called every `sampling_rate` by the thread
+/// that started the search.
 pub trait Debugger<Board: Clone + BarracudaBoard<Params, Move>, Params: Send, Move: Copy> {
-    fn new() -> Self;
+    fn sampling_rate(&self) -> Duration;
+
+    fn start(&mut self, _board: &Board) {}
 
-    fn sampling_rate() -> Duration;
+    fn debug(&mut self, board: &Board, info: &SearchInfo<Move>);
 
-    fn debug(board: &Board, eval: f32, nodes: u32, depth: u32, pv: &[Move]);
+    fn end(&mut self, _board: &Board, _info: &SearchInfo<Move>, _reason: StopReason) {}
 }
 
+#[derive(Default)]
 pub struct NoDebug;
 
 impl<Board: Clone + BarracudaBoard<Params, Move>, Params: Send, Move: Copy>
     Debugger<Board, Params, Move> for NoDebug
 {
-    fn new() -> Self {
-        Self {}
-    }
-
-    fn sampling_rate() -> Duration {
-        Duration::from_secs_f32(f32::INFINITY)
+    fn sampling_rate(&self) -> Duration {
+        Duration::MAX
     }
 
-    fn debug(_: &Board, _: f32, _: u32, _: u32, _: &[Move]) {}
+    fn debug(&mut self, _: &Board, _: &SearchInfo<Move>) {}
 }
 
+/// Human readable lines on the standard output.
+#[derive(Default)]
 pub struct BarracudaDebug;
 
 impl<Board: Clone + BarracudaBoard<Params, Move>, Params: Send, Move: Copy>
     Debugger<Board, Params, Move> for BarracudaDebug
 {
-    fn new() -> Self {
-        Self {}
-    }
-
-    fn sampling_rate() -> Duration {
+    fn sampling_rate(&self) -> Duration {
         Duration::from_secs_f32(0.2)
     }
 
-    fn debug(board: &Board, eval: f32, nodes: u32, depth: u32, pv: &[Move]) {
-        print!("pwin: {} visits: {} depth: {} ", eval, nodes, depth);
+    fn debug(&mut self, board: &Board, info: &SearchInfo<Move>) {
+        let eval = info.eval;
+        match eval.proof {
+            Proof::Unknown => print!("pwin: {} ", eval.pwin),
+            Proof::Win => print!("win proven "),
+            Proof::Loss => print!("loss proven "),
+            Proof::Draw => print!("draw proven "),
+        }
+        if let Some((margin, pessimistic, optimistic)) = eval.margin {
+            print!("margin: {:.2} [{}, {}] ", margin, pessimistic, optimistic);
+        }
+        print!(
+            "time: {:.1}s visits: {} nps: {} depth: {} avg {:.1} ",
+            info.elapsed.as_secs_f32(),
+            info.visits,
+            info.nps as u32,
+            info.max_depth,
+            info.average_depth
+        );
+        let tree = info.tree;
+        print!(
+            "tree: {} nodes {:.1} MB ",
+            tree.size,
+            tree.memory as f32 / (1024f32 * 1024f32)
+        );
+        if tree.transpositions > 0 {
+            print!("transpositions: {} ", tree.transpositions);
+        }
         print!("pv: ");
-        for mv in pv {
+        for mv in &info.pv {
             print!("{} ", board.move_to_str(*mv));
         }
         println!();
+        //A single line is the pv above
+        if info.multi_pv > 1 {
+            for (index, line) in info.children.iter().take(info.multi_pv).enumerate() {
+                print!(
+                    "  multipv {}: {} visits: {} value: {:.3} prior: {:.3} pv: ",
+                    index + 1,
+                    board.move_to_str(line.mv),
+                    line.visits,
+                    line.value,
+                    line.prior
+                );
+                for mv in &line.pv {
+                    print!("{} ", board.move_to_str(*mv));
+                }
+                println!();
+            }
+        }
+    }
+
+    fn end(&mut self, board: &Board, info: &SearchInfo<Move>, reason: StopReason) {
+        self.debug(board, info);
+        println!("stopped: {:?}", reason);
+    }
+}
+
+/// One JSON object per line for other programs, `{"event": "start"}`, then an `"info"` event
+/// every `sampling_rate` and a last `"end"` event with the `"reason"` the search stopped.
+pub struct JsonDebug<W: Write> {
+    out: W,
+}
+
+impl<W: Write> JsonDebug<W> {
+    pub fn new(out: W) -> Self {
+        Self { out }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn write(&mut self, line: String) {
+        //Telemetry is not worth failing the search over
+        let _ = writeln!(self.out, "{}", line);
+        let _ = self.out.flush();
+    }
+}
+
+impl Default for JsonDebug<Stdout> {
+    fn default() -> Self {
+        Self::new(std::io::stdout())
+    }
+}
+
+/// `value` as a JSON number, which has no infinities or NaN.
+pub(crate) fn json_number(value: f32) -> String {
+    if value.is_finite() {
+        value.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+pub(crate) fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn json_moves<Board: BarracudaBoard<Params, Move>, Params, Move: Copy>(
+    board: &Board,
+    moves: &[Move],
+) -> String {
+    let moves = moves
+        .iter()
+        .map(|mv| json_string(&board.move_to_str(*mv)))
+        .collect::<Vec<_>>();
+    format!("[{}]", moves.join(","))
+}
+
+/// Fields of an `"info"` or `"end"` event.
+fn json_info<Board: BarracudaBoard<Params, Move>, Params, Move: Copy>(
+    board: &Board,
+    info: &SearchInfo<Move>,
+) -> String {
+    let margin = match info.eval.margin {
+        Some((margin, pessimistic, optimistic)) => format!(
+            "[{},{},{}]",
+            json_number(margin),
+            json_number(pessimistic),
+            json_number(optimistic)
+        ),
+        None => "null".to_string(),
+    };
+    let children = info
+        .children
+        .iter()
+        .map(|line| {
+            format!(
+                "{{\"move\":{},\"visits\":{},\"value\":{},\"prior\":{},\"proof\":\"{:?}\",\"pv\":{}}}",
+                json_string(&board.move_to_str(line.mv)),
+                line.visits,
+                json_number(line.value),
+                json_number(line.prior),
+                line.proof,
+                json_moves(board, &line.pv)
+            )
+        })
+        .collect::<Vec<_>>();
+    format!(
+        "\"elapsed\":{},\"iterations\":{},\"nps\":{},\"visits\":{},\"max_depth\":{},\"average_depth\":{},\"pwin\":{},\"proof\":\"{:?}\",\"margin\":{},\"tree\":{{\"size\":{},\"memory\":{},\"transpositions\":{}}},\"pv\":{},\"children\":[{}]",
+        json_number(info.elapsed.as_secs_f32()),
+        info.iterations,
+        json_number(info.nps),
+        info.visits,
+        info.max_depth,
+        json_number(info.average_depth),
+        json_number(info.eval.pwin),
+        info.eval.proof,
+        margin,
+        info.tree.size,
+        info.tree.memory,
+        info.tree.transpositions,
+        json_moves(board, &info.pv),
+        children.join(",")
+    )
+}
+
+impl<Board: Clone + BarracudaBoard<Params, Move>, Params: Send, Move: Copy, W: Write>
+    Debugger<Board, Params, Move> for JsonDebug<W>
+{
+    fn sampling_rate(&self) -> Duration {
+        Duration::from_secs_f32(0.2)
+    }
+
+    fn start(&mut self, _: &Board) {
+        self.write("{\"event\":\"start\"}".to_string());
+    }
+
+    fn debug(&mut self, board: &Board, info: &SearchInfo<Move>) {
+        let line = format!("{{\"event\":\"info\",{}}}", json_info(board, info));
+        self.write(line);
+    }
+
+    fn end(&mut self, board: &Board, info: &SearchInfo<Move>, reason: StopReason) {
+        let line = format!(
+            "{{\"event\":\"end\",\"reason\":\"{:?}\",{}}}",
+            reason,
+            json_info(board, info)
+        );
+        self.write(line);
     }
 }
@@ -0,0 +1,249 @@
+use crate::barracuda::b_core::algorithms::ucb::formula::Term;
+use crate::barracuda::debugger::{json_number, json_string};
+use crate::barracuda::mcts::{BarracudaAlgorithms, NodeRef, Proof, Tree, ROOT};
+use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
+use std::io::{Result, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TreeFormat {
+    /// Graphviz graph, one box per node.
+    Dot,
+    /// Nested objects, each with its `"children"`.
+    Json,
+}
+
+/// Writes the part of a tree a search visited the most, for seeing what a UCB formula made of
+/// it. Nodes below `max_depth` plies or with fewer than `min_visits` visits are cut along with
+/// their subtrees, see `BarracudaRunner::export_tree`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TreeExport {
+    pub format: TreeFormat,
+    pub max_depth: usize,
+    pub min_visits: u32,
+}
+
+/// A node as exported, with the UCB its parent scored it with.
+struct Exported {
+    mv: Option<String>,
+    visits: f32,
+    /// Sum of the scores for the player to move at the node, see `Node::eval`.
+    score: f32,
+    prior: f32,
+    proof: Proof,
+    registers: Vec<f32>,
+    /// UCB broken down into its terms, see `Formula::explain`, and whether it came from the first
+    /// play urgency, which scores unvisited children.
+    ucb: Option<(Term, bool)>,
+    children: Vec<Exported>,
+}
+
+impl TreeExport {
+    pub fn new(format: TreeFormat, max_depth: usize, min_visits: u32) -> Self {
+        Self {
+            format,
+            max_depth,
+            min_visits,
+        }
+    }
+
+    pub fn write<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+        W: Write,
+    >(
+        &self,
+        tree: &Tree<Board, Params, Move, DATA_REGISTERS>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        out: &mut W,
+    ) -> Result<()> {
+        let root = {
+            let nodes = tree.read();
+            let root = NodeRef::new(&nodes, ROOT);
+            let mut exported = Self::node(root, None, None);
+            exported.children = self.children(root, algo, 1);
+            exported
+        };
+        match self.format {
+            TreeFormat::Dot => {
+                writeln!(out, "digraph tree {{")?;
+                writeln!(out, "    node [shape=box];")?;
+                Self::write_dot(&root, &mut 0, out)?;
+                writeln!(out, "}}")
+            }
+            TreeFormat::Json => {
+                Self::write_json(&root, out)?;
+                writeln!(out)
+            }
+        }
+    }
+
+    fn node<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    >(
+        node: NodeRef<Board, Params, Move, DATA_REGISTERS>,
+        mv: Option<String>,
+        ucb: Option<(Term, bool)>,
+    ) -> Exported {
+        Exported {
+            mv,
+            visits: node.visits.get(),
+            score: node.score.get(),
+            prior: node.prior(),
+            proof: node.proof(),
+            registers: node
+                .registers
+                .iter()
+                .map(|register| register.get())
+                .collect(),
+            ucb,
+            children: vec![],
+        }
+    }
+
+    /// Children of `node` at `depth` that make the cut, scored the way `Node::select` scores them.
+    fn children<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    >(
+        &self,
+        node: NodeRef<Board, Params, Move, DATA_REGISTERS>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        depth: usize,
+    ) -> Vec<Exported> {
+        if depth > self.max_depth {
+            return vec![];
+        }
+        let children = node
+            .children()
+            .filter(|(_, child)| child.visits.get() >= self.min_visits as f32)
+            .collect::<Vec<_>>();
+        let mut exported = {
+            //Released before going down, the lock isn't reentrant
+            let _ucb_lock = algo.ucb.lock();
+            children
+                .iter()
+                .map(|(mv, child)| {
+                    algo.set_node(*child, mv);
+                    let ucb = if child.visits.get() > 0f32 {
+                        (algo.ucb.explain(), false)
+                    } else {
+                        (algo.ucb.explain_first_play_urgency(), true)
+                    };
+                    Self::node(*child, Some(node.board().move_to_str(*mv)), Some(ucb))
+                })
+                .collect::<Vec<_>>()
+        };
+        for (exported, (_, child)) in exported.iter_mut().zip(children) {
+            exported.children = self.children(child, algo, depth + 1);
+        }
+        exported
+    }
+
+    /// Writes `node` and its subtree, numbering nodes from `next`.
+    fn write_dot<W: Write>(node: &Exported, next: &mut usize, out: &mut W) -> Result<()> {
+        let id = *next;
+        *next += 1;
+        let mut label = format!(
+            "{}\\nvisits: {}\\nscore: {:.2}\\nprior: {:.3}",
+            Self::escape_dot(node.mv.as_deref().unwrap_or("root")),
+            node.visits,
+            node.score,
+            node.prior
+        );
+        if let Some((ucb, first_play_urgency)) = &node.ucb {
+            label += &format!("\\nucb: {:.3}", ucb.value);
+            if *first_play_urgency {
+                label += " (fpu)";
+            }
+            for term in &ucb.terms {
+                Self::write_dot_term(term, 1, &mut label);
+            }
+        }
+        if node.proof != Proof::Unknown {
+            label += &format!("\\nproof: {:?}", node.proof);
+        }
+        for (index, register) in node.registers.iter().enumerate() {
+            label += &format!("\\nr{}: {:.3}", index, register);
+        }
+        writeln!(out, "    n{} [label=\"{}\"];", id, label)?;
+        for child in &node.children {
+            writeln!(out, "    n{} -> n{};", id, *next)?;
+            Self::write_dot(child, next, out)?;
+        }
+        Ok(())
+    }
+
+    /// Labels are quoted, so their quotes and backslashes are escaped.
+    fn escape_dot(text: &str) -> String {
+        text.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    /// Adds a line per term to a DOT label, indented by `depth` below the UCB.
+    fn write_dot_term(term: &Term, depth: usize, label: &mut String) {
+        *label += &format!(
+            "\\n{:indent$}{}: {:.3}",
+            "",
+            Self::escape_dot(&term.label),
+            term.value,
+            indent = 2 * depth
+        );
+        for term in &term.terms {
+            Self::write_dot_term(term, depth + 1, label);
+        }
+    }
+
+    /// `{"label":..,"value":..,"terms":[..]}`, see `Term`.
+    fn json_term(term: &Term) -> String {
+        let terms = term.terms.iter().map(Self::json_term).collect::<Vec<_>>();
+        format!(
+            "{{\"label\":{},\"value\":{},\"terms\":[{}]}}",
+            json_string(&term.label),
+            json_number(term.value),
+            terms.join(",")
+        )
+    }
+
+    fn write_json<W: Write>(node: &Exported, out: &mut W) -> Result<()> {
+        let (ucb, first_play_urgency) = match &node.ucb {
+            Some((ucb, first_play_urgency)) => (Self::json_term(ucb), *first_play_urgency),
+            None => ("null".to_string(), false),
+        };
+        let registers = node
+            .registers
+            .iter()
+            .map(|register| json_number(*register))
+            .collect::<Vec<_>>();
+        write!(
+            out,
+            "{{\"move\":{},\"visits\":{},\"score\":{},\"prior\":{},\"proof\":\"{:?}\",\"registers\":[{}],\"ucb\":{},\"first_play_urgency\":{},\"children\":[",
+            node.mv.as_deref().map_or("null".to_string(), json_string),
+            json_number(node.visits),
+            json_number(node.score),
+            json_number(node.prior),
+            node.proof,
+            registers.join(","),
+            ucb,
+            first_play_urgency
+        )?;
+        for (index, child) in node.children.iter().enumerate() {
+            if index > 0 {
+                write!(out, ",")?;
+            }
+            Self::write_json(child, out)?;
+        }
+        write!(out, "]}}")
+    }
+}
+
+impl Default for TreeExport {
+    fn default() -> Self {
+        Self::new(TreeFormat::Dot, 3, 1)
+    }
+}
@@ -1,11 +1,34 @@
 use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams, GameState, Player};
 
+use crate::barracuda::atomic::AtomicF32;
+use crate::barracuda::b_core::algorithms::ucb::fpu::{FirstPlayUrgency, Urgency};
 use crate::barracuda::b_core::components::backprop::Backprop;
+use crate::barracuda::b_core::components::batch_eval::BatchEval;
 use crate::barracuda::b_core::components::policy::Policy;
 use crate::barracuda::b_core::components::simulate::Simulation;
+use crate::barracuda::b_core::components::static_eval::StaticEval;
 use crate::barracuda::b_core::components::ucb::Ucb;
 use crate::barracuda::b_core::params::{BarracudaSimulationParams, BarracudaUcbParams};
-use std::sync::{Arc, Mutex, Weak};
+use rand::Rng;
+use std::borrow::Cow;
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::marker::PhantomData;
+use std::mem::{replace, size_of};
+use std::ops::{Deref, Range};
+use std::sync::atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock, RwLock, RwLockReadGuard};
+
+/// Components the UCB formula reads, given each child before it gets scored.
+pub type UcbAlgorithms<Board, Params, Move, const DATA_REGISTERS: usize> =
+    Vec<Arc<Mutex<dyn BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }>>>>;
+/// Components given every position a simulation goes through.
+pub type SimulationAlgorithms<Board, Params, Move, const DATA_REGISTERS: usize> =
+    Vec<Arc<Mutex<dyn BarracudaSimulationParams<Board, Params, Move, { DATA_REGISTERS }>>>>;
+/// Components given the result of every iteration on its way up.
+pub type BackpropAlgorithms<Board, Params, Move, const DATA_REGISTERS: usize> =
+    Vec<Arc<Mutex<dyn Backprop<Board, Params, Move, { DATA_REGISTERS }>>>>;
 
 pub struct BarracudaAlgorithms<
     Board: BarracudaBoard<Params, Move>,
@@ -13,14 +36,314 @@ pub struct BarracudaAlgorithms<
     Move: BarracudaMove,
     const DATA_REGISTERS: usize,
 > {
-    pub ucb_algorithms:
-        Vec<Arc<Mutex<dyn BarracudaUcbParams<Board, Params, Move, { DATA_REGISTERS }>>>>,
+    pub ucb_algorithms: UcbAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
     pub ucb: Ucb,
     pub policy: Arc<Mutex<dyn Policy<Board, Params, Move>>>,
-    pub simulation_algorithms:
-        Vec<Arc<Mutex<dyn BarracudaSimulationParams<Board, Params, Move, { DATA_REGISTERS }>>>>,
+    pub simulation_algorithms: SimulationAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
     pub simulation: Arc<Mutex<dyn Simulation<Board, Params, Move>>>,
-    pub backprop_algorithms: Vec<Arc<Mutex<dyn Backprop<Board, Params, Move, { DATA_REGISTERS }>>>>,
+    pub backprop_algorithms: BackpropAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+}
+
+impl<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > BarracudaAlgorithms<Board, Params, Move, DATA_REGISTERS>
+{
+    /// Scores children that were never visited with `urgency` instead of the UCB formula. Without
+    /// it, every child is tried once in move order.
+    pub fn set_first_play_urgency(&mut self, urgency: Urgency<Board, Params, Move>) {
+        let first_play_urgency = Arc::new(Mutex::new(FirstPlayUrgency::new(urgency)));
+        self.ucb_algorithms.push(first_play_urgency.clone());
+        self.ucb.set_first_play_urgency(first_play_urgency);
+    }
+
+    /// Gives the UCB components a child reached with `mv` before it gets scored. Children that
+    /// were never visited only go to the components that score them, see
+    /// `BarracudaUcbParams::scores_unvisited`.
+    pub fn set_node(&self, child: NodeRef<Board, Params, Move, { DATA_REGISTERS }>, mv: &Move) {
+        let visited = child.visits.get() > 0f32;
+        for algorithm in &self.ucb_algorithms {
+            let mut algorithm = algorithm.lock().unwrap();
+            if visited || algorithm.scores_unvisited() {
+                algorithm.set_node(child, mv);
+            }
+        }
+    }
+}
+
+/// Index of the root in every `Tree`.
+pub const ROOT: usize = 0;
+
+/// Share of the budget a tree is pruned down to once it is full.
+const PRUNE_TARGET: f32 = 0.75;
+
+/// Index of an outcome drawn with the given probabilities.
+pub fn sample_outcome(probabilities: impl IntoIterator<Item = f32>) -> usize {
+    let mut remaining = rand::thread_rng().gen::<f32>();
+    let mut index = 0;
+    for (outcome, probability) in probabilities.into_iter().enumerate() {
+        index = outcome;
+        remaining -= probability;
+        if remaining < 0f32 {
+            break;
+        }
+    }
+    index
+}
+
+/// Game theoretic value of a node for the player to move there, once the search has proven it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Proof {
+    Unknown,
+    Win,
+    Loss,
+    Draw,
+}
+
+impl Proof {
+    /// Result of a finished game for the player to move in the final position.
+    fn from_game_state(game_state: GameState, turn: Player) -> Self {
+        match game_state {
+            GameState::Ongoing => Proof::Unknown,
+            GameState::End(None) => Proof::Draw,
+            GameState::End(Some(winner)) if winner == turn => Proof::Win,
+            GameState::End(Some(_)) => Proof::Loss,
+        }
+    }
+
+    fn from_score(score: f32) -> Self {
+        if score > 0f32 {
+            Proof::Win
+        } else if score < 0f32 {
+            Proof::Loss
+        } else {
+            Proof::Draw
+        }
+    }
+
+    /// The same proof seen by the other player.
+    fn flip(self) -> Self {
+        match self {
+            Proof::Win => Proof::Loss,
+            Proof::Loss => Proof::Win,
+            proof => proof,
+        }
+    }
+
+    pub fn score(self) -> Option<f32> {
+        match self {
+            Proof::Unknown => None,
+            Proof::Win => Some(1f32),
+            Proof::Loss => Some(0f32),
+            Proof::Draw => Some(0.5f32),
+        }
+    }
+}
+
+/// `Proof` stored in an `AtomicU8`, so it can be set during backpropagation.
+struct AtomicProof(AtomicU8);
+
+impl AtomicProof {
+    fn new(proof: Proof) -> Self {
+        Self(AtomicU8::new(proof as u8))
+    }
+
+    fn get(&self) -> Proof {
+        match self.0.load(Ordering::Relaxed) {
+            1 => Proof::Win,
+            2 => Proof::Loss,
+            3 => Proof::Draw,
+            _ => Proof::Unknown,
+        }
+    }
+
+    fn set(&self, proof: Proof) {
+        self.0.store(proof as u8, Ordering::Relaxed);
+    }
+}
+
+impl Clone for AtomicProof {
+    fn clone(&self) -> Self {
+        Self::new(self.get())
+    }
+}
+
+impl Debug for AtomicProof {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self.get())
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NodeBudget {
+    Nodes(usize),
+    Bytes(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum BudgetPolicy {
+    /// Leaves are simulated without being expanded, refining the statistics already in the tree.
+    StopExpanding,
+    /// The subtrees of the least visited nodes are dropped to make room for new ones.
+    Prune,
+}
+
+/// Order in which the moves of a node become selectable under progressive widening.
+pub enum WideningOrder<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+> {
+    /// Highest prior first, see `Node::prior`.
+    Prior,
+    /// Best static evaluation for the player making the move first.
+    StaticEval(Arc<Mutex<dyn StaticEval<Board, Params, Move>>>),
+}
+
+impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove> Clone
+    for WideningOrder<Board, Params, Move>
+{
+    fn clone(&self) -> Self {
+        match self {
+            WideningOrder::Prior => WideningOrder::Prior,
+            WideningOrder::StaticEval(static_eval) => {
+                WideningOrder::StaticEval(static_eval.clone())
+            }
+        }
+    }
+}
+
+/// Lets a decision node with N visits select only its first k * N^alpha children in `order`,
+/// for games with large branching factors. Children are created as they are needed, in
+/// batches doubling the number the node already has.
+pub struct ProgressiveWidening<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+> {
+    pub k: f32,
+    pub alpha: f32,
+    pub order: WideningOrder<Board, Params, Move>,
+}
+
+impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove>
+    ProgressiveWidening<Board, Params, Move>
+{
+    pub fn new(k: f32, alpha: f32, order: WideningOrder<Board, Params, Move>) -> Self {
+        Self { k, alpha, order }
+    }
+
+    /// Number of selectable children of a node with `visits` visits.
+    fn width(&self, visits: f32) -> usize {
+        (self.k * visits.max(1f32).powf(self.alpha))
+            .ceil()
+            .max(1f32) as usize
+    }
+}
+
+impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove> Clone
+    for ProgressiveWidening<Board, Params, Move>
+{
+    fn clone(&self) -> Self {
+        Self::new(self.k, self.alpha, self.order.clone())
+    }
+}
+
+/// Nodes of a search tree stored in a single arena. The children of a node occupy a contiguous
+/// range of the arena, statistics are atomics, and the arena is only locked for writing when a
+/// node gets expanded.
+pub struct Tree<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    nodes: RwLock<Vec<Node<Board, Params, Move, { DATA_REGISTERS }>>>,
+    //Held by every search iteration, pruning takes it exclusively since it renumbers nodes
+    iteration: RwLock<()>,
+    budget: Budget,
+    //Whether a terminal score was seen, otherwise margins are meaningless
+    scored: AtomicBool,
+    players: usize,
+    transpositions: Transpositions<Board>,
+    widening: Option<ProgressiveWidening<Board, Params, Move>>,
+    paths: Paths,
+}
+
+/// Size limit of a tree and the memory it uses.
+struct Budget {
+    limit: Option<(NodeBudget, BudgetPolicy)>,
+    memory: AtomicUsize,
+    //Set when a node or board didn't fit, until the tree gets pruned
+    full: AtomicBool,
+}
+
+/// Positions of a tree searched as a DAG, see `Tree::set_transpositions`.
+struct Transpositions<Board> {
+    //First node reaching every position, only kept when searching a DAG
+    positions: Option<Mutex<HashMap<Board, usize>>>,
+    //Children linked to an existing node
+    merged: AtomicUsize,
+}
+
+/// Lengths of the paths selected since the root was set.
+#[derive(Default)]
+struct Paths {
+    longest: AtomicUsize,
+    count: AtomicUsize,
+    lengths: AtomicUsize,
+}
+
+impl Paths {
+    fn add(&self, length: usize) {
+        self.longest.fetch_max(length, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.lengths.fetch_add(length, Ordering::Relaxed);
+    }
+}
+
+enum Leaf<
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    Proven(Rollout),
+    Terminal(Rollout),
+    /// Children to give the node, with the bytes they take and the priors of the moves left
+    /// without one by widening.
+    Expand {
+        moves: Vec<Move>,
+        children: Vec<Node<Board, Params, Move, { DATA_REGISTERS }>>,
+        memory: usize,
+        chance: bool,
+        priors: Option<Vec<f32>>,
+    },
+    Full(Option<Move>, Board),
+}
+
+/// Path selected by an iteration, whether its leaf is proven and bounded, and the leaf's result
+/// or the node, last move and position to simulate from.
+struct Descent<Board, Move> {
+    nodes: Vec<usize>,
+    proven: bool,
+    bounded: bool,
+    leaf: Outcome<Board, Move>,
+}
+
+enum Outcome<Board, Move> {
+    Known(Rollout),
+    Pending(usize, Option<Move>, Board),
+}
+
+/// Result of a simulation or finished game. `score` and `margin` are seen from the player to
+/// move at the simulated position, `rewards` are only given in games with more than two players.
+struct Rollout {
+    score: f32,
+    margin: Option<f32>,
+    rewards: Vec<f32>,
 }
 
 #[derive(Debug, Clone)]
@@ -30,15 +353,60 @@ pub struct Node<
     Move: BarracudaMove,
     const DATA_REGISTERS: usize,
 > {
-    pub board: Board,
+    /// Position at the node, only computed once the search needs it, see `Tree::board`.
+    board: OnceLock<Board>,
     pub moves: Vec<Move>,
-    pub child_nodes: Vec<Arc<Mutex<Node<Board, Params, Move, { DATA_REGISTERS }>>>>,
-    pub parent: Option<Weak<Mutex<Node<Board, Params, Move, { DATA_REGISTERS }>>>>,
+    pub children: Range<usize>,
+    pub parent: Option<usize>,
+    /// Whether nature moves at the node, its children being the outcomes of a chance event.
+    pub chance: bool,
+    /// Probability of the chance outcome leading to the node, 1 below decision nodes. Links keep
+    /// the one of their own parent, read it through `NodeRef::probability`.
+    probability: f32,
+    /// Prior probability of the move leading to the node, from the policy of a `BatchEval` if it
+    /// gave one for the parent, otherwise from `Policy::priors`. Links keep the one of their own
+    /// parent, read it through `NodeRef::prior`.
+    prior: f32,
+    /// Policy over the moves of the node from a `BatchEval`, kept until the node is expanded.
+    pub priors: Vec<f32>,
+    /// Node that reached the same position first and holds its statistics and children, when
+    /// searching a DAG. The node itself is only a link and is never visited.
+    pub transposition: Option<usize>,
 
-    pub score: f32,
-    pub visits: f32,
+    pub score: AtomicF32,
+    pub visits: AtomicF32,
+    /// Times the node was legal while its parent was visited, only counted by information set
+    /// searches, where moves may be legal in some determinizations only.
+    pub availability: AtomicF32,
 
-    pub registers: [f32; DATA_REGISTERS],
+    /// Sum of the terminal scores reached through the node, for the player to move.
+    pub margin: AtomicF32,
+    /// Sum of the rewards of every player, only kept in games with more than two players.
+    pub rewards: Vec<AtomicF32>,
+    /// Bounds on the terminal score under perfect play, for the player to move.
+    pub pessimistic: AtomicF32,
+    pub optimistic: AtomicF32,
+
+    pub registers: [AtomicF32; DATA_REGISTERS],
+    proof: AtomicProof,
+    p: PhantomData<Params>,
+}
+
+/// A node together with the arena it lives in, so algorithms can walk to its parent and
+/// children. In a DAG the parent is the node the child was reached from.
+pub struct NodeRef<
+    'a,
+    Board: BarracudaBoard<Params, Move>,
+    Params: BarracudaParams,
+    Move: BarracudaMove,
+    const DATA_REGISTERS: usize,
+> {
+    nodes: &'a [Node<Board, Params, Move, { DATA_REGISTERS }>],
+    id: usize,
+    parent: Option<usize>,
+    /// Child of `parent` the node was reached through, a link to it in a DAG, which holds the
+    /// data of the move from `parent`.
+    edge: usize,
 }
 
 impl<
@@ -46,47 +414,728 @@ impl<
         Params: BarracudaParams,
         Move: BarracudaMove,
         const DATA_REGISTERS: usize,
-    > Node<Board, Params, Move, DATA_REGISTERS>
+    > Tree<Board, Params, Move, DATA_REGISTERS>
 {
-    pub fn new(
-        board: Board,
-        parent: Option<Weak<Mutex<Node<Board, Params, Move, { DATA_REGISTERS }>>>>,
+    pub fn new(board: Board) -> Self {
+        let players = board.players();
+        let root = Node::new(board, None);
+        Self {
+            budget: Budget {
+                limit: None,
+                memory: AtomicUsize::new(root.memory()),
+                full: AtomicBool::new(false),
+            },
+            nodes: RwLock::new(vec![root]),
+            iteration: RwLock::new(()),
+            scored: AtomicBool::new(false),
+            players,
+            transpositions: Transpositions {
+                positions: None,
+                merged: AtomicUsize::new(0),
+            },
+            widening: None,
+            paths: Paths::default(),
+        }
+    }
+
+    pub fn set_budget(&mut self, budget: Option<(NodeBudget, BudgetPolicy)>) {
+        self.budget.limit = budget;
+    }
+
+    /// Turns the tree into a DAG: children reaching a position that is already in the tree link
+    /// to its node instead of getting their own, see `Node::transposition`. The shared node
+    /// keeps the statistics of every path through it, so each simulation is counted once per
+    /// position whichever way it got there. Positions must not repeat within a game.
+    pub fn set_transpositions(&mut self, transpositions: bool) {
+        let nodes = self.nodes.get_mut().unwrap();
+        self.transpositions.positions = transpositions.then(|| Mutex::new(Self::positions(nodes)));
+    }
+
+    pub fn set_progressive_widening(
+        &mut self,
+        widening: Option<ProgressiveWidening<Board, Params, Move>>,
+    ) {
+        self.widening = widening;
+    }
+
+    /// Children that were linked to an existing node instead of being added, see
+    /// `set_transpositions`.
+    pub fn transpositions(&self) -> usize {
+        self.transpositions.merged.load(Ordering::Relaxed)
+    }
+
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Length of the longest path searched since the root was set.
+    pub fn depth(&self) -> usize {
+        self.paths.longest.load(Ordering::Relaxed)
+    }
+
+    /// Average length of the paths searched since the root was set.
+    pub fn average_depth(&self) -> f32 {
+        let paths = self.paths.count.load(Ordering::Relaxed);
+        if paths == 0 {
+            return 0f32;
+        }
+        self.paths.lengths.load(Ordering::Relaxed) as f32 / paths as f32
+    }
+
+    /// Estimated bytes used by the nodes, see `Node::memory`.
+    pub fn memory(&self) -> usize {
+        self.budget.memory.load(Ordering::Relaxed)
+    }
+
+    /// Whether the game reports terminal scores, see `BarracudaBoard::terminal_score`.
+    pub fn scored(&self) -> bool {
+        self.scored.load(Ordering::Relaxed)
+    }
+
+    pub fn solved(&self) -> bool {
+        self.read()[ROOT].solved(self.scored())
+    }
+
+    pub fn read(&self) -> RwLockReadGuard<'_, Vec<Node<Board, Params, Move, { DATA_REGISTERS }>>> {
+        self.nodes.read().unwrap()
+    }
+
+    /// Looks for a node with `board` at most `depth` plies below the root.
+    pub fn find(&self, board: &Board, depth: usize) -> Option<usize> {
+        let nodes = self.read();
+        let mut layer = vec![ROOT];
+        for _ in 0..=depth {
+            let mut next_layer = vec![];
+            for id in layer {
+                if *NodeRef::new(&nodes, id).board() == *board {
+                    return Some(id);
+                }
+                next_layer.extend(
+                    nodes[id]
+                        .children
+                        .clone()
+                        .map(|child| Self::resolve(&nodes, child)),
+                );
+            }
+            layer = next_layer;
+        }
+        None
+    }
+
+    /// Keeps only the subtree below `id`, which becomes the new root.
+    pub fn reroot(&mut self, id: usize) {
+        let nodes = self.nodes.get_mut().unwrap();
+        let memory = Self::compact(nodes, id, &[]);
+        self.budget.memory.store(memory, Ordering::Relaxed);
+        self.paths = Paths::default();
+        if let Some(transpositions) = &mut self.transpositions.positions {
+            *transpositions.get_mut().unwrap() = Self::positions(nodes);
+        }
+    }
+
+    /// The node `id` links to, or `id` itself. Links to relocated nodes may lead to further links.
+    fn resolve(nodes: &[Node<Board, Params, Move, { DATA_REGISTERS }>], mut id: usize) -> usize {
+        while let Some(next) = nodes[id].transposition {
+            id = next;
+        }
+        id
+    }
+
+    /// Move leading to `id` from the parent that created it, none at the root.
+    fn last_move(
+        nodes: &[Node<Board, Params, Move, { DATA_REGISTERS }>],
+        id: usize,
+    ) -> Option<Move> {
+        nodes[id]
+            .parent
+            .map(|parent| nodes[parent].moves[id - nodes[parent].children.start])
+    }
+
+    /// Number of selectable children of a decision node, see `ProgressiveWidening`.
+    fn width(&self, node: &Node<Board, Params, Move, { DATA_REGISTERS }>) -> usize {
+        match &self.widening {
+            Some(widening) if !node.chance => widening.width(node.visits.get()),
+            _ => usize::MAX,
+        }
+    }
+
+    /// Orders `moves` and their `priors` for progressive widening, the first move becoming
+    /// selectable first.
+    fn widening_order(&self, board: &Board, moves: &mut Vec<Move>, priors: &mut Vec<f32>) {
+        let keys = match self.widening.as_ref().map(|widening| &widening.order) {
+            None => return,
+            Some(WideningOrder::Prior) => priors.clone(),
+            Some(WideningOrder::StaticEval(static_eval)) => {
+                let mut static_eval = static_eval.lock().unwrap();
+                moves
+                    .iter()
+                    .map(|mv| {
+                        let mut new_board = board.clone();
+                        new_board.make_move(*mv);
+                        let eval = static_eval.evaluate(&new_board);
+                        if new_board.turn() == board.turn() {
+                            eval
+                        } else {
+                            -eval
+                        }
+                    })
+                    .collect()
+            }
+        };
+        let mut order = (0..moves.len()).collect::<Vec<_>>();
+        order.sort_by(|&a, &b| keys[b].total_cmp(&keys[a]));
+        *moves = order.iter().map(|&index| moves[index]).collect();
+        *priors = order.iter().map(|&index| priors[index]).collect();
+    }
+
+    /// Children of `node` that widening wants to exist, counting children proven lost for the
+    /// player to move as unselectable.
+    fn wanted_children(&self, node: NodeRef<Board, Params, Move, DATA_REGISTERS>) -> usize {
+        let width = self.width(&node);
+        if width == usize::MAX || node.moves.len() == node.children.len() {
+            return node.children.len();
+        }
+        let lost = node
+            .children()
+            .filter(|(_, child)| !self.scored() && child.parent_proof() == Proof::Loss)
+            .count();
+        (width + lost).min(node.moves.len())
+    }
+
+    /// Creates more children of `parent` once widening wants them, doubling the number it has.
+    fn widen(&self, nodes: &mut Vec<Node<Board, Params, Move, { DATA_REGISTERS }>>, parent: usize) {
+        let wanted = self.wanted_children(NodeRef::new(nodes, parent));
+        let node = &nodes[parent];
+        let created = node.children.len();
+        if wanted <= created {
+            return;
+        }
+        let end = wanted.max(2 * created).min(node.moves.len());
+        let mut child_nodes = vec![];
+        for index in created..end {
+            let mut child = self.child(NodeRef::new(nodes, parent), node.moves[index]);
+            child.prior = node.priors.get(index).copied().unwrap_or(1f32);
+            child_nodes.push(child);
+        }
+        let memory = child_nodes
+            .iter()
+            .map(|child| child.memory())
+            .sum::<usize>();
+        if !self.has_room(nodes.len(), child_nodes.len(), memory) {
+            return;
+        }
+        self.merge_transpositions(nodes.len() + created, &mut child_nodes);
+        let placeholders = Self::add_children(nodes, parent, child_nodes);
+        self.budget
+            .memory
+            .fetch_add(memory + placeholders, Ordering::Relaxed);
+    }
+
+    /// Position of `node`, computing it now if it is still unknown. A computed board is kept, and
+    /// its memory added to the tree, if the budget has room for it. This is the only place boards
+    /// get kept, `NodeRef::board` computes them without keeping them.
+    fn board<'b>(&self, node: NodeRef<'b, Board, Params, Move, DATA_REGISTERS>) -> Cow<'b, Board> {
+        let lazy = &node.nodes[node.id].board;
+        if let Some(board) = lazy.get() {
+            return Cow::Borrowed(board);
+        }
+        let board = node.next_board();
+        let memory = board.heap_size();
+        if !self.has_room(node.nodes.len(), 0, memory) {
+            return Cow::Owned(board);
+        }
+        let mut kept = false;
+        let board = lazy.get_or_init(|| {
+            kept = true;
+            board
+        });
+        if kept {
+            self.budget.memory.fetch_add(memory, Ordering::Relaxed);
+        }
+        Cow::Borrowed(board)
+    }
+
+    /// New child of `parent` reached with `mv`. Its board is computed once it gets selected, or
+    /// right away when searching a DAG, to look its position up.
+    fn child(
+        &self,
+        parent: NodeRef<Board, Params, Move, DATA_REGISTERS>,
+        mv: Move,
+    ) -> Node<Board, Params, Move, { DATA_REGISTERS }> {
+        if self.transpositions.positions.is_some() {
+            let mut new_board = parent.board().into_owned();
+            new_board.make_move(mv);
+            Node::new(new_board, Some(parent.id()))
+        } else {
+            Node::create(OnceLock::new(), Some(parent.id()), self.players)
+        }
+    }
+
+    /// First node of every position in the arena, skipping links and nodes without a board.
+    fn positions(nodes: &[Node<Board, Params, Move, { DATA_REGISTERS }>]) -> HashMap<Board, usize> {
+        let mut positions = HashMap::new();
+        for (id, node) in nodes.iter().enumerate() {
+            if let (None, Some(board)) = (node.transposition, node.board.get()) {
+                positions.entry(board.clone()).or_insert(id);
+            }
+        }
+        positions
+    }
+
+    /// Links the new children of a node starting at index `start` to the nodes already holding
+    /// their positions, and records the positions seen for the first time.
+    fn merge_transpositions(
+        &self,
+        start: usize,
+        child_nodes: &mut [Node<Board, Params, Move, { DATA_REGISTERS }>],
+    ) {
+        let mut positions = match &self.transpositions.positions {
+            Some(transpositions) => transpositions.lock().unwrap(),
+            None => return,
+        };
+        for (index, child) in child_nodes.iter_mut().enumerate() {
+            //Children of a DAG always get their board, see `child`
+            match positions.entry(child.board.get().unwrap().clone()) {
+                Entry::Occupied(entry) => {
+                    child.transposition = Some(*entry.get());
+                    self.transpositions.merged.fetch_add(1, Ordering::Relaxed);
+                }
+                Entry::Vacant(entry) => {
+                    entry.insert(start + index);
+                }
+            }
+        }
+    }
+
+    /// Gives `parent` the additional children `child_nodes`, reached with the moves following
+    /// those of its current children. Children that already exist are moved next to the new ones
+    /// to keep the range contiguous, leaving placeholders linking to them behind until the arena
+    /// gets compacted. Returns the memory of the placeholders.
+    fn add_children(
+        nodes: &mut Vec<Node<Board, Params, Move, { DATA_REGISTERS }>>,
+        parent: usize,
+        child_nodes: Vec<Node<Board, Params, Move, { DATA_REGISTERS }>>,
+    ) -> usize {
+        let mut memory = 0;
+        let start = nodes.len();
+        for child in nodes[parent].children.clone() {
+            let moved = nodes.len();
+            let mut stale = Node::create(OnceLock::new(), None, 0);
+            //Workers still holding the old index find the node through the link
+            stale.transposition = Some(moved);
+            memory += stale.memory();
+            let child_node = replace(&mut nodes[child], stale);
+            for grandchild in child_node.children.clone() {
+                nodes[grandchild].parent = Some(moved);
+            }
+            nodes.push(child_node);
+        }
+        nodes.extend(child_nodes);
+        let end = nodes.len();
+        nodes[parent].children = start..end;
+        memory
+    }
+
+    /// Whether `nodes` new nodes using `memory` bytes fit into a tree of `len` nodes.
+    fn has_room(&self, len: usize, nodes: usize, memory: usize) -> bool {
+        let has_room = match self.budget.limit {
+            None => true,
+            Some((NodeBudget::Nodes(max_nodes), _)) => len + nodes <= max_nodes,
+            Some((NodeBudget::Bytes(max_bytes), _)) => self.memory() + memory <= max_bytes,
+        };
+        if !has_room {
+            self.budget.full.store(true, Ordering::Relaxed);
+        }
+        has_room
+    }
+
+    fn needs_pruning(&self) -> bool {
+        matches!(self.budget.limit, Some((_, BudgetPolicy::Prune)))
+            && self.budget.full.load(Ordering::Relaxed)
+    }
+
+    /// Collapses the subtrees of the least visited nodes until the tree is back to
+    /// `PRUNE_TARGET` of its budget. Waits for running iterations, as node indices change.
+    fn prune(&self) {
+        let _iteration = self.iteration.write().unwrap();
+        if !self.budget.full.swap(false, Ordering::Relaxed) {
+            return;
+        }
+        let mut nodes = self.nodes.write().unwrap();
+        let (budget, count_bytes) = match self.budget.limit {
+            Some((NodeBudget::Nodes(max_nodes), _)) => (max_nodes, false),
+            Some((NodeBudget::Bytes(max_bytes), _)) => (max_bytes, true),
+            None => return,
+        };
+        let cost = |node: &Node<Board, Params, Move, DATA_REGISTERS>| {
+            if count_bytes {
+                node.memory()
+            } else {
+                1
+            }
+        };
+        let subtree_cost = Self::subtree_costs(&nodes, cost);
+        let mut total = subtree_cost[ROOT];
+        let target = (budget as f32 * PRUNE_TARGET) as usize;
+        let mut candidates = (0..nodes.len())
+            .filter(|&id| id != ROOT && !nodes[id].children.is_empty())
+            .collect::<Vec<_>>();
+        candidates.sort_by(|&a, &b| nodes[a].visits.get().total_cmp(&nodes[b].visits.get()));
+        let mut pruned = vec![false; nodes.len()];
+        for id in candidates {
+            if total <= target {
+                break;
+            }
+            let mut ancestor = nodes[id].parent;
+            let mut inside_pruned = false;
+            while let Some(parent) = ancestor {
+                inside_pruned |= pruned[parent];
+                ancestor = nodes[parent].parent;
+            }
+            if !inside_pruned {
+                pruned[id] = true;
+                total -= subtree_cost[id] - cost(&nodes[id]);
+            }
+        }
+        let memory = Self::compact(&mut nodes, ROOT, &pruned);
+        self.budget.memory.store(memory, Ordering::Relaxed);
+        if let Some(transpositions) = &self.transpositions.positions {
+            *transpositions.lock().unwrap() = Self::positions(&nodes);
+        }
+    }
+
+    /// Cost of the subtree below each node reachable from the root, 0 for the others. Widening
+    /// moves children behind their own descendants, so the arena is walked from the root rather
+    /// than in reverse.
+    fn subtree_costs(
+        nodes: &[Node<Board, Params, Move, { DATA_REGISTERS }>],
+        cost: impl Fn(&Node<Board, Params, Move, { DATA_REGISTERS }>) -> usize,
+    ) -> Vec<usize> {
+        let mut order = vec![ROOT];
+        let mut index = 0;
+        while index < order.len() {
+            order.extend(nodes[order[index]].children.clone());
+            index += 1;
+        }
+        let mut subtree_cost = vec![0; nodes.len()];
+        for &id in order.iter().rev() {
+            subtree_cost[id] += cost(&nodes[id]);
+            if let Some(parent) = nodes[id].parent {
+                subtree_cost[parent] += subtree_cost[id];
+            }
+        }
+        subtree_cost
+    }
+
+    /// Rebuilds the arena from the subtree below `root`, dropping the children of `pruned` nodes.
+    /// The first link to a dropped node takes its place. Returns the memory of the remaining
+    /// nodes.
+    fn compact(
+        nodes: &mut Vec<Node<Board, Params, Move, { DATA_REGISTERS }>>,
+        root: usize,
+        pruned: &[bool],
+    ) -> usize {
+        for id in 0..nodes.len() {
+            if let Some(link) = nodes[id].transposition {
+                nodes[id].transposition = Some(Self::resolve(nodes, link));
+            }
+        }
+        let mut old_nodes = nodes.drain(..).map(Some).collect::<Vec<_>>();
+        let mut old_ids = vec![root];
+        let mut root = old_nodes[root].take().unwrap();
+        root.parent = None;
+        nodes.push(root);
+        //Breadth first, so every child range stays contiguous
+        let mut index = 0;
+        while index < nodes.len() {
+            let children = nodes[index].children.clone();
+            let start = nodes.len();
+            if pruned.get(old_ids[index]).copied().unwrap_or(false) {
+                nodes[index].moves = vec![];
+            } else {
+                for child in children {
+                    let mut child_node = old_nodes[child].take().unwrap();
+                    child_node.parent = Some(index);
+                    nodes.push(child_node);
+                    old_ids.push(child);
+                }
+            }
+            nodes[index].children = start..nodes.len();
+            index += 1;
+        }
+        let mut new_ids = vec![None; old_nodes.len()];
+        for (new_id, &old_id) in old_ids.iter().enumerate() {
+            new_ids[old_id] = Some(new_id);
+        }
+        for (id, node) in nodes.iter_mut().enumerate() {
+            if let Some(old_id) = node.transposition {
+                node.transposition = new_ids[old_id];
+                new_ids[old_id].get_or_insert(id);
+            }
+        }
+        nodes.iter().map(|node| node.memory()).sum()
+    }
+}
+
+impl<
+        'a,
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > NodeRef<'a, Board, Params, Move, DATA_REGISTERS>
+{
+    pub fn new(nodes: &'a [Node<Board, Params, Move, { DATA_REGISTERS }>], id: usize) -> Self {
+        Self::reached_from(nodes, id, nodes[id].parent, id)
+    }
+
+    /// The node as seen from `parent`, which may be any of its parents in a DAG, through the
+    /// child `edge` of `parent`.
+    fn reached_from(
+        nodes: &'a [Node<Board, Params, Move, { DATA_REGISTERS }>],
+        id: usize,
+        parent: Option<usize>,
+        edge: usize,
     ) -> Self {
+        Self {
+            nodes,
+            id,
+            parent,
+            edge,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    pub fn parent(&self) -> Option<Self> {
+        self.parent.map(|parent| Self::new(self.nodes, parent))
+    }
+
+    /// Position at the node. A child the search hasn't kept a board for yet gets a fresh one from
+    /// its parent, which isn't kept, so the memory of the tree stays within its budget, see
+    /// `Tree::board`.
+    pub fn board(&self) -> Cow<'a, Board> {
+        match self.nodes[self.id].board.get() {
+            Some(board) => Cow::Borrowed(board),
+            None => Cow::Owned(self.next_board()),
+        }
+    }
+
+    /// Board of the parent that created the node after the move leading to the node.
+    fn next_board(&self) -> Board {
+        //Nodes only get children once their own board is known
+        let parent = Self::new(self.nodes, self.nodes[self.id].parent.unwrap());
+        let mut board = parent.board().into_owned();
+        board.make_move(parent.moves[self.id - parent.children.start]);
+        board
+    }
+
+    /// Prior of the move from the parent the node was reached from, see `Node::prior`.
+    pub fn prior(&self) -> f32 {
+        self.nodes[self.edge].prior
+    }
+
+    /// Probability of the chance outcome from the parent the node was reached from, see
+    /// `Node::probability`.
+    pub fn probability(&self) -> f32 {
+        self.nodes[self.edge].probability
+    }
+
+    /// Whether the player to move differs from the one at the parent, in which case statistics
+    /// have to be flipped to be seen from the parent.
+    pub fn flipped(&self) -> bool {
+        self.parent()
+            .is_none_or(|parent| parent.board().turn() != self.board().turn())
+    }
+
+    /// Counts `virtual_loss` visits of the node as lost for the player to move at the parent, who
+    /// may also be the one to move at the node, so the workers descending at the same time spread
+    /// out. A negative `virtual_loss` takes them back.
+    fn add_virtual_loss(&self, virtual_loss: f32) {
+        if self.flipped() {
+            self.score.add(virtual_loss);
+        }
+        self.visits.add(virtual_loss);
+    }
+
+    /// Visits of the parent while the node could be selected, see `Node::availability`. The root
+    /// has none.
+    pub fn parent_visits(&self) -> f32 {
+        match self.parent() {
+            Some(_) if self.availability.get() > 0f32 => self.availability.get(),
+            Some(parent) => parent.visits.get(),
+            None => 0f32,
+        }
+    }
+
+    /// Proof of the node for the player to move at the parent.
+    pub fn parent_proof(&self) -> Proof {
+        //Other proofs look the same to both players, which spares computing the board of a fresh
+        //child
+        match self.proof() {
+            proof @ (Proof::Win | Proof::Loss) if self.flipped() => proof.flip(),
+            proof => proof,
+        }
+    }
+
+    /// Pessimistic and optimistic score bounds for the player to move at the parent.
+    pub fn parent_bounds(&self) -> (f32, f32) {
+        let (pessimistic, optimistic) = (self.pessimistic.get(), self.optimistic.get());
+        //Bounds symmetric around 0, such as those of a fresh child, look the same to both players
+        if pessimistic != -optimistic && self.flipped() {
+            (-optimistic, -pessimistic)
+        } else {
+            (pessimistic, optimistic)
+        }
+    }
+
+    /// Children with their moves, links being replaced by the nodes they link to.
+    pub fn children(&self) -> impl Iterator<Item = (Move, Self)> + 'a {
+        let nodes = self.nodes;
+        let id = self.id;
+        let node = &nodes[id];
+        node.moves
+            .iter()
+            .copied()
+            .zip(node.children.clone())
+            .map(move |(mv, edge)| {
+                let child = Tree::resolve(nodes, edge);
+                (mv, Self::reached_from(nodes, child, Some(id), edge))
+            })
+    }
+}
+
+impl<
+        'a,
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > Clone for NodeRef<'a, Board, Params, Move, DATA_REGISTERS>
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<
+        'a,
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > Copy for NodeRef<'a, Board, Params, Move, DATA_REGISTERS>
+{
+}
+
+impl<
+        'a,
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > Deref for NodeRef<'a, Board, Params, Move, DATA_REGISTERS>
+{
+    type Target = Node<Board, Params, Move, DATA_REGISTERS>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.nodes[self.id]
+    }
+}
+
+impl<
+        Board: BarracudaBoard<Params, Move>,
+        Params: BarracudaParams,
+        Move: BarracudaMove,
+        const DATA_REGISTERS: usize,
+    > Node<Board, Params, Move, DATA_REGISTERS>
+{
+    pub fn new(board: Board, parent: Option<usize>) -> Self {
+        let players = board.players();
+        Self::create(OnceLock::from(board), parent, players)
+    }
+
+    fn create(board: OnceLock<Board>, parent: Option<usize>, players: usize) -> Self {
+        let rewards = if players > 2 {
+            (0..players).map(|_| AtomicF32::new(0.0)).collect()
+        } else {
+            vec![]
+        };
         Self {
             board,
             moves: vec![],
-            child_nodes: vec![],
+            children: 0..0,
             parent,
-            score: 0.0,
-            visits: 1e-8,
-            registers: [0.0; DATA_REGISTERS],
+            chance: false,
+            probability: 1.0,
+            prior: 1.0,
+            priors: vec![],
+            transposition: None,
+            score: AtomicF32::new(0.0),
+            visits: AtomicF32::new(0.0),
+            availability: AtomicF32::new(0.0),
+            margin: AtomicF32::new(0.0),
+            rewards,
+            pessimistic: AtomicF32::new(f32::NEG_INFINITY),
+            optimistic: AtomicF32::new(f32::INFINITY),
+            registers: [(); DATA_REGISTERS].map(|_| AtomicF32::new(0.0)),
+            proof: AtomicProof::new(Proof::Unknown),
+            p: PhantomData,
         }
     }
 
+    /// Average score for the player to move, 0 until the node is visited.
     pub fn eval(&self) -> f32 {
-        self.score / self.visits
+        let visits = self.visits.get();
+        if visits > 0f32 {
+            self.score.get() / visits
+        } else {
+            0f32
+        }
     }
 
     pub fn visits(&self) -> u32 {
-        self.visits as u32
+        self.visits.get() as u32
+    }
+
+    pub fn proof(&self) -> Proof {
+        self.proof.get()
+    }
+
+    /// Whether the bounds met, so the terminal score under perfect play is known.
+    pub fn bounded(&self) -> bool {
+        self.pessimistic.get() == self.optimistic.get()
+    }
+
+    /// Whether searching below the node is pointless. With terminal scores the exact score has to
+    /// be known, not only the winner.
+    pub fn solved(&self, scored: bool) -> bool {
+        self.proof() != Proof::Unknown && (!scored || self.bounded())
+    }
+
+    /// Estimated bytes used by the node, including the heap memory of its board once computed and
+    /// of its moves.
+    pub fn memory(&self) -> usize {
+        size_of::<Self>()
+            + self.board.get().map_or(0, |board| board.heap_size())
+            + self.moves.capacity() * size_of::<Move>()
+            + self.rewards.capacity() * size_of::<AtomicF32>()
+            + self.priors.capacity() * size_of::<f32>()
     }
 
-    pub fn pv(node: Arc<Mutex<Node<Board, Params, Move, DATA_REGISTERS>>>) -> Vec<Move> {
+    pub fn pv(node: NodeRef<Board, Params, Move, DATA_REGISTERS>) -> Vec<Move> {
         let mut moves = vec![];
         let mut current_node = node;
-        while !current_node.lock().unwrap().moves.is_empty() {
+        //Bounded by the arena in case positions repeat in a DAG
+        while !current_node.moves.is_empty() && moves.len() < current_node.nodes.len() {
             let mut highest_visits = -1f32;
             let mut best_move = None;
-            let current_node_lock = current_node.lock().unwrap();
-            let child_nodes = current_node_lock.child_nodes.clone();
-            let child_moves = current_node_lock.moves.clone();
-            drop(current_node_lock);
-
-            for (child, mv) in child_nodes.into_iter().zip(child_moves.into_iter()) {
-                let child_borrow = child.lock().unwrap();
-                if child_borrow.visits > highest_visits {
-                    highest_visits = child_borrow.visits;
-                    current_node = child.clone();
+            for (mv, child) in current_node.children() {
+                if child.visits.get() > highest_visits {
+                    highest_visits = child.visits.get();
+                    current_node = child;
                     best_move = Some(mv);
                 }
             }
@@ -97,107 +1146,949 @@ impl<
         moves
     }
 
+    /// Minimax over the proofs of the children: a child lost for the opponent wins, and the node
+    /// is only lost or drawn once every child is proven. Chance nodes are proven once every
+    /// outcome has the same proof.
+    fn backup_proof(node: NodeRef<Board, Params, Move, DATA_REGISTERS>) -> Proof {
+        if node.chance {
+            let mut proofs = node.children().map(|(_, child)| child.parent_proof());
+            let first = proofs.next().unwrap_or(Proof::Unknown);
+            return if proofs.all(|proof| proof == first) {
+                first
+            } else {
+                Proof::Unknown
+            };
+        }
+        //Moves without a child yet, under progressive widening, are unknown
+        let mut all_proven = node.children.len() == node.moves.len();
+        let mut draw = false;
+        for (_, child) in node.children() {
+            match child.parent_proof() {
+                Proof::Win => return Proof::Win,
+                Proof::Draw => draw = true,
+                Proof::Loss => {}
+                Proof::Unknown => all_proven = false,
+            }
+        }
+        match (all_proven, draw) {
+            (false, _) => Proof::Unknown,
+            (true, true) => Proof::Draw,
+            (true, false) => Proof::Loss,
+        }
+    }
+
+    /// Child of a decision node with the highest UCB, among the `legal` ones if given and the
+    /// first `width` that are still worth exploring.
+    fn select(
+        node: NodeRef<Board, Params, Move, DATA_REGISTERS>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        scored: bool,
+        legal: Option<&[Move]>,
+        width: usize,
+    ) -> Option<usize> {
+        let mut highest_ucb = f32::NEG_INFINITY;
+        let mut selected_node = None;
+        let pessimistic = node.pessimistic.get();
+        let _ucb_lock = algo.ucb.lock();
+        let mut selectable = 0;
+        for (mv, child) in node.children() {
+            if legal.is_some_and(|legal| !legal.contains(&mv)) {
+                continue;
+            }
+            //Proven losses are never worth exploring, and neither are children that can't score
+            //as much as the node already guarantees
+            if (!scored && child.parent_proof() == Proof::Loss)
+                || child.parent_bounds().1 < pessimistic
+            {
+                continue;
+            }
+            if selectable == width {
+                break;
+            }
+            selectable += 1;
+            algo.set_node(child, &mv);
+            let ucb = if child.visits.get() > 0f32 {
+                algo.ucb.ucb()
+            } else {
+                algo.ucb.first_play_urgency()
+            };
+            if ucb > highest_ucb || selected_node.is_none() {
+                highest_ucb = ucb;
+                selected_node = Some(child.id());
+            }
+        }
+        selected_node
+    }
+
     pub fn search(
-        node: Arc<Mutex<Node<Board, Params, Move, { DATA_REGISTERS }>>>,
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
         algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        virtual_loss: f32,
     ) {
-        //Select
-        let mut nodes = vec![];
-        let mut current_node = node;
-        nodes.push(current_node.clone());
-        while !current_node.lock().unwrap().moves.is_empty() {
-            let mut highest_ucb = f32::NEG_INFINITY;
-
-            let current_node_lock = current_node.lock().unwrap();
-            let child_nodes = current_node_lock.child_nodes.clone();
-            let moves = current_node_lock.moves.clone();
-            drop(current_node_lock);
-            let mut selected_node = None;
-            for (child, mv) in child_nodes.into_iter().zip(moves.into_iter()) {
-                let mut child_lock = child.lock().unwrap();
-                for algorithm in &algo.ucb_algorithms {
-                    algorithm.lock().unwrap().set_node(&mut child_lock, &mv);
-                }
-                let ucb = algo.ucb.ucb();
-                if ucb > highest_ucb || selected_node.is_none() {
-                    highest_ucb = ucb;
-                    selected_node = Some(child.clone());
-                }
-            }
-            if let Some(selected_node) = selected_node {
-                current_node = selected_node;
-                nodes.push(current_node.clone());
-            } else {
-                println!("#WARNING: NO SELECTIONS OCCURRED")
+        let iteration = tree.iteration.read().unwrap();
+        let descent = Self::descend(tree, algo, virtual_loss);
+        let rollout = match descent.leaf {
+            Outcome::Known(rollout) => rollout,
+            Outcome::Pending(node, last_move, board) => {
+                Self::simulate(tree, algo, node, last_move, &board)
             }
+        };
+        Self::backpropagate(
+            tree,
+            algo,
+            &descent.nodes,
+            rollout,
+            virtual_loss,
+            descent.proven,
+            descent.bounded,
+        );
+        Self::widen(tree, &descent.nodes);
+        drop(iteration);
+        if tree.needs_pruning() {
+            tree.prune();
         }
-        //Expand
-        let mut rollout_score;
-        {
-            let mut node = current_node.lock().unwrap();
-            let moves = node.board.get_moves();
-            let game_state = node.board.game_state();
-            if moves.is_empty() || !matches!(game_state, GameState::Ongoing) {
-                rollout_score = match game_state {
-                    GameState::Ongoing => {
-                        panic!()
+    }
+
+    /// Descends `batch_size` times, each path holding virtual losses so they spread out, before
+    /// evaluating every leaf with a single call to `evaluator` and backing the paths up. Leaves
+    /// are evaluated instead of simulated, so simulation algorithms see none of them.
+    pub fn search_batch(
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        evaluator: &Mutex<dyn BatchEval<Board, Params, Move>>,
+        batch_size: usize,
+        virtual_loss: f32,
+    ) {
+        let iteration = tree.iteration.read().unwrap();
+        //The root is never a leaf to evaluate, so its policy is asked for before it gets expanded
+        let root = {
+            let nodes = tree.read();
+            let root = NodeRef::new(&nodes, ROOT);
+            let unknown = root.moves.is_empty() && root.priors.is_empty();
+            Some(root.board().into_owned())
+                .filter(|board| unknown && matches!(board.game_state(), GameState::Ongoing))
+        };
+        if let Some(root) = root {
+            let evaluation = evaluator.lock().unwrap().evaluate(&[root]).remove(0);
+            Self::set_priors(tree, ROOT, evaluation.priors);
+        }
+        let descents = (0..batch_size)
+            .map(|_| Self::descend(tree, algo, virtual_loss))
+            .collect::<Vec<_>>();
+        let boards = descents
+            .iter()
+            .filter_map(|descent| match &descent.leaf {
+                Outcome::Pending(_, _, board) => Some(board.clone()),
+                Outcome::Known(_) => None,
+            })
+            .collect::<Vec<_>>();
+        let mut evaluations = if boards.is_empty() {
+            vec![]
+        } else {
+            evaluator.lock().unwrap().evaluate(&boards)
+        }
+        .into_iter();
+        for descent in descents {
+            let rollout = match descent.leaf {
+                Outcome::Known(rollout) => rollout,
+                Outcome::Pending(node, _, board) => {
+                    let evaluation = evaluations.next().unwrap();
+                    Self::set_priors(tree, node, evaluation.priors);
+                    Self::evaluated(tree, &board, evaluation.value)
+                }
+            };
+            Self::backpropagate(
+                tree,
+                algo,
+                &descent.nodes,
+                rollout,
+                virtual_loss,
+                descent.proven,
+                descent.bounded,
+            );
+            Self::widen(tree, &descent.nodes);
+        }
+        drop(iteration);
+        if tree.needs_pruning() {
+            tree.prune();
+        }
+    }
+
+    /// Gives the nodes on a path the children progressive widening wants them to have.
+    fn widen(tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>, nodes: &[usize]) {
+        if tree.widening.is_none() {
+            return;
+        }
+        let narrow = {
+            let tree_nodes = tree.read();
+            nodes
+                .iter()
+                .map(|&node| Tree::resolve(&tree_nodes, node))
+                .filter(|&node| {
+                    let node = NodeRef::new(&tree_nodes, node);
+                    tree.wanted_children(node) > node.children.len()
+                })
+                .collect::<Vec<_>>()
+        };
+        if narrow.is_empty() {
+            return;
+        }
+        let mut tree_nodes = tree.nodes.write().unwrap();
+        for node in narrow {
+            //Widening a node moves the children of the nodes before it
+            let node = Tree::resolve(&tree_nodes, node);
+            tree.widen(&mut tree_nodes, node);
+        }
+    }
+
+    /// Keeps the policy of `node` until it gets expanded.
+    fn set_priors(
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
+        node: usize,
+        priors: Option<Vec<f32>>,
+    ) {
+        if let Some(priors) = priors {
+            let mut tree_nodes = tree.nodes.write().unwrap();
+            let node = Tree::resolve(&tree_nodes, node);
+            if tree_nodes[node].moves.is_empty() {
+                tree.budget
+                    .memory
+                    .fetch_add(priors.capacity() * size_of::<f32>(), Ordering::Relaxed);
+                tree_nodes[node].priors = priors;
+            }
+        }
+    }
+
+    /// Result of an evaluated leaf, in games with more than two players the other players share
+    /// what the player to move does not get.
+    fn evaluated(
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
+        board: &Board,
+        value: f32,
+    ) -> Rollout {
+        let mut rewards = vec![];
+        if tree.players > 2 {
+            rewards = vec![(1f32 - value) / (tree.players - 1) as f32; tree.players];
+            rewards[board.turn().index()] = value;
+        }
+        Rollout {
+            score: value,
+            margin: None,
+            rewards,
+        }
+    }
+
+    /// Selects a path from the root, expanding its last node, up to the position to simulate.
+    /// Every node below the root gets a virtual loss, see `NodeRef::add_virtual_loss`, until the
+    /// path is backed up.
+    fn descend(
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        virtual_loss: f32,
+    ) -> Descent<Board, Move> {
+        //Select
+        let mut nodes = vec![ROOT];
+        let mut current_node = ROOT;
+        let mut repeated = false;
+        let leaf = {
+            let tree_nodes = tree.read();
+            let scored = tree.scored();
+            while !tree_nodes[current_node].moves.is_empty()
+                && !tree_nodes[current_node].solved(scored)
+            {
+                let node = NodeRef::new(&tree_nodes, current_node);
+                let selected_node = if node.chance {
+                    let outcome =
+                        sample_outcome(node.children().map(|(_, child)| child.probability()));
+                    node.children().nth(outcome).map(|(_, child)| child.id())
+                } else {
+                    Self::select(node, algo, scored, None, tree.width(&node))
+                };
+                //A position repeating in a DAG is simulated from where it came back
+                if selected_node.is_some_and(|selected_node| nodes.contains(&selected_node)) {
+                    repeated = true;
+                    break;
+                }
+                if let Some(selected_node) = selected_node {
+                    NodeRef::new(&tree_nodes, selected_node).add_virtual_loss(virtual_loss);
+                    current_node = selected_node;
+                    nodes.push(current_node);
+                } else {
+                    //Every child is lost
+                    let node = NodeRef::new(&tree_nodes, current_node);
+                    node.proof.set(Self::backup_proof(node));
+                    break;
+                }
+            }
+            let board = tree.board(NodeRef::new(&tree_nodes, current_node));
+            let node = &tree_nodes[current_node];
+            let game_state = board.game_state();
+            if node.solved(scored) {
+                Leaf::Proven(Rollout {
+                    score: node.proof().score().unwrap(),
+                    margin: Some(node.pessimistic.get()).filter(|_| node.bounded()),
+                    rewards: vec![],
+                })
+            } else if repeated {
+                Leaf::Full(
+                    Tree::last_move(&tree_nodes, current_node),
+                    board.into_owned(),
+                )
+            } else if matches!(game_state, GameState::Ongoing) {
+                let outcomes = board.chance_outcomes();
+                let chance = !outcomes.is_empty();
+                let mut moves = if chance {
+                    outcomes.iter().map(|(mv, _)| *mv).collect()
+                } else {
+                    board.get_moves()
+                };
+                let mut priors = if chance {
+                    outcomes
+                        .iter()
+                        .map(|(_, probability)| *probability)
+                        .collect()
+                } else if node.priors.len() == moves.len() {
+                    node.priors.clone()
+                } else {
+                    algo.policy.lock().unwrap().priors(&board, &moves)
+                };
+                let width = tree.width(node);
+                if !chance {
+                    tree.widening_order(&board, &mut moves, &mut priors);
+                }
+                let mut child_nodes = vec![];
+                for (index, mv) in moves.iter().enumerate().take(width) {
+                    let mut child = tree.child(NodeRef::new(&tree_nodes, current_node), *mv);
+                    child.prior = priors[index];
+                    if chance {
+                        child.probability = outcomes[index].1;
                     }
-                    GameState::End(color) => {
-                        if let Some(color) = color {
-                            match color {
-                                Player::P1 => 1f32,
-                                Player::P2 => 0f32,
-                            }
-                        } else {
-                            0.5f32
-                        }
+                    child_nodes.push(child);
+                }
+                let memory = child_nodes
+                    .iter()
+                    .map(|child| child.memory())
+                    .sum::<usize>()
+                    + moves.capacity() * size_of::<Move>();
+                if tree.has_room(tree_nodes.len(), child_nodes.len(), memory) {
+                    //Widening keeps the priors of the moves without a child yet
+                    let priors = Some(priors).filter(|_| width < moves.len());
+                    Leaf::Expand {
+                        moves,
+                        children: child_nodes,
+                        memory,
+                        chance,
+                        priors,
                     }
-                };
-                if node.board.turn() == Player::P1 {
-                    rollout_score = 1f32 - rollout_score;
+                } else {
+                    Leaf::Full(
+                        Tree::last_move(&tree_nodes, current_node),
+                        board.into_owned(),
+                    )
                 }
             } else {
-                let mut child_nodes = vec![];
-                for mv in &moves {
-                    let mut new_board = node.board.clone();
-                    new_board.make_move(*mv);
-                    let child_node =
-                        Node::new(new_board, Some(Arc::downgrade(&current_node.clone())));
-                    child_nodes.push(Arc::new(Mutex::new(child_node)));
-                }
-
-                let index = algo.policy.lock().unwrap().pick(&node.board, &moves);
-                let move_made = moves[index];
-                let child_node = child_nodes[index].clone();
-                node.child_nodes = child_nodes;
-                node.moves = moves;
-                drop(node);
+                let rollout = Self::terminal(tree, &board);
+                //Proofs and bounds are two player notions
+                if tree.players > 2 {
+                    Leaf::Terminal(rollout)
+                } else {
+                    if let Some(margin) = rollout.margin {
+                        node.pessimistic.set(margin);
+                        node.optimistic.set(margin);
+                    }
+                    node.proof
+                        .set(Proof::from_game_state(game_state, board.turn()));
+                    Leaf::Proven(rollout)
+                }
+            }
+        };
+        let (proven, bounded) = match &leaf {
+            Leaf::Proven(rollout) => (true, rollout.margin.is_some()),
+            _ => (false, false),
+        };
+        //Expand
+        let leaf = match leaf {
+            Leaf::Proven(rollout) | Leaf::Terminal(rollout) => Outcome::Known(rollout),
+            Leaf::Expand {
+                moves,
+                children: mut child_nodes,
+                memory,
+                chance,
+                priors,
+            } => {
+                if moves.is_empty() {
+                    panic!()
+                }
                 {
-                    let child_node = &mut child_node.lock().unwrap();
-                    let rollout = algo.simulation.lock().unwrap().simulate(&child_node.board);
-                    rollout_score = match child_node.board.turn() {
-                        Player::P1 => rollout,
-                        Player::P2 => 1f32 - rollout,
-                    };
-                    for algorithm in &algo.simulation_algorithms {
-                        algorithm
-                            .lock()
-                            .unwrap()
-                            .set_node(child_node, &move_made, rollout_score)
+                    let mut tree_nodes = tree.nodes.write().unwrap();
+                    //The node may have been moved by widening, or expanded by another worker,
+                    //while this one was selecting it
+                    current_node = Tree::resolve(&tree_nodes, current_node);
+                    if tree_nodes[current_node].moves.is_empty() {
+                        let start = tree_nodes.len();
+                        tree.merge_transpositions(start, &mut child_nodes);
+                        tree.budget.memory.fetch_add(memory, Ordering::Relaxed);
+                        tree_nodes.extend(child_nodes);
+                        let end = tree_nodes.len();
+                        let node = &mut tree_nodes[current_node];
+                        node.children = start..end;
+                        node.moves = moves;
+                        node.chance = chance;
+                        let old_priors = replace(&mut node.priors, priors.unwrap_or_default());
+                        tree.budget.memory.fetch_add(
+                            node.priors.capacity() * size_of::<f32>(),
+                            Ordering::Relaxed,
+                        );
+                        tree.budget
+                            .memory
+                            .fetch_sub(old_priors.capacity() * size_of::<f32>(), Ordering::Relaxed);
                     }
                 }
-                nodes.push(child_node);
+                let tree_nodes = tree.read();
+                current_node = Tree::resolve(&tree_nodes, current_node);
+                let board = tree.board(NodeRef::new(&tree_nodes, current_node));
+                let node = &tree_nodes[current_node];
+                let index = if node.chance {
+                    let children = &tree_nodes[node.children.clone()];
+                    sample_outcome(children.iter().map(|child| child.probability))
+                } else {
+                    let selectable = node.children.len().min(tree.width(node));
+                    algo.policy
+                        .lock()
+                        .unwrap()
+                        .pick(&board, &node.moves[..selectable])
+                };
+                let child_node = Tree::resolve(&tree_nodes, node.children.start + index);
+                //A child linking back to the path is simulated from its parent, like a position
+                //repeating while selecting
+                if nodes.contains(&child_node) {
+                    let last_move = Tree::last_move(&tree_nodes, current_node);
+                    Outcome::Pending(current_node, last_move, board.into_owned())
+                } else {
+                    let child = NodeRef::new(&tree_nodes, child_node);
+                    child.add_virtual_loss(virtual_loss);
+                    nodes.push(child_node);
+                    Outcome::Pending(
+                        child_node,
+                        Some(node.moves[index]),
+                        tree.board(child).into_owned(),
+                    )
+                }
+            }
+            Leaf::Full(last_move, board) => Outcome::Pending(current_node, last_move, board),
+        };
+        Descent {
+            nodes,
+            proven,
+            bounded,
+            leaf,
+        }
+    }
+
+    /// Single-observer information set search. Every iteration descends the tree with a new
+    /// determinization of the root, sampled from what the player to move there knows. Nodes stand
+    /// for the moves played rather than positions, so only the children legal in the current
+    /// determinization compete, and moves showing up for the first time are added to the node.
+    /// Results depend on the determinization, so nothing gets proven.
+    pub fn search_information_set(
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        virtual_loss: f32,
+    ) {
+        let iteration = tree.iteration.read().unwrap();
+        let mut nodes = vec![ROOT];
+        let mut current_node = ROOT;
+        let mut board = {
+            let nodes = tree.read();
+            let root = NodeRef::new(&nodes, ROOT).board();
+            root.determinize(root.turn())
+        };
+        //Select
+        let leaf = loop {
+            if !matches!(board.game_state(), GameState::Ongoing) {
+                break Self::terminal(tree, &board);
+            }
+            let outcomes = board.chance_outcomes();
+            let chance = !outcomes.is_empty();
+            let legal = if chance {
+                let outcome = sample_outcome(outcomes.iter().map(|(_, probability)| *probability));
+                vec![outcomes[outcome]]
+            } else {
+                board.get_moves().into_iter().map(|mv| (mv, 1f32)).collect()
+            };
+            let legal_moves = legal.iter().map(|(mv, _)| *mv).collect::<Vec<_>>();
+            let tree_nodes = tree.read();
+            let node = NodeRef::new(&tree_nodes, current_node);
+            let mut untried = vec![];
+            for &(mv, probability) in &legal {
+                if !node.moves.contains(&mv) {
+                    untried.push((mv, probability));
+                }
+            }
+            for (mv, child) in node.children() {
+                if legal_moves.contains(&mv) {
+                    child.availability.add(1f32);
+                }
+            }
+            if untried.is_empty() {
+                let selected_node = if chance {
+                    node.children()
+                        .find(|(mv, _)| *mv == legal_moves[0])
+                        .map(|(_, child)| child.id())
+                } else {
+                    Self::select(node, algo, false, Some(&legal_moves), usize::MAX)
+                };
+                let selected_node = selected_node.unwrap();
+                NodeRef::new(&tree_nodes, selected_node).add_virtual_loss(virtual_loss);
+                board
+                    .make_move(tree_nodes[current_node].moves[selected_node - node.children.start]);
+                current_node = selected_node;
+                nodes.push(current_node);
+                continue;
+            }
+            //Expand
+            let priors = if chance {
+                legal.iter().map(|(_, probability)| *probability).collect()
+            } else {
+                algo.policy.lock().unwrap().priors(&board, &legal_moves)
+            };
+            let mut moves = vec![];
+            let mut child_nodes = vec![];
+            for &(mv, probability) in &untried {
+                let mut new_board = board.clone();
+                new_board.make_move(mv);
+                let mut child = Node::new(new_board, Some(current_node));
+                child.probability = probability;
+                child.prior = priors[legal_moves.iter().position(|legal| *legal == mv).unwrap()];
+                child.availability.set(1f32);
+                moves.push(mv);
+                child_nodes.push(child);
+            }
+            let memory = child_nodes
+                .iter()
+                .map(|child| child.memory())
+                .sum::<usize>()
+                + moves.len() * size_of::<Move>();
+            if !tree.has_room(tree_nodes.len(), child_nodes.len(), memory) {
+                let last_move = Tree::last_move(&tree_nodes, current_node);
+                drop(tree_nodes);
+                break Self::simulate(tree, algo, current_node, last_move, &board);
             }
+            drop(tree_nodes);
+            let index = if chance {
+                0
+            } else {
+                algo.policy.lock().unwrap().pick(&board, &moves)
+            };
+            let move_made = moves[index];
+            let child_node = {
+                let mut tree_nodes = tree.nodes.write().unwrap();
+                let placeholders = Tree::add_children(&mut tree_nodes, current_node, child_nodes);
+                tree.budget
+                    .memory
+                    .fetch_add(memory + placeholders, Ordering::Relaxed);
+                let node = &mut tree_nodes[current_node];
+                node.moves.extend(moves);
+                node.chance = chance;
+                let position = node.moves.iter().position(|mv| *mv == move_made).unwrap();
+                node.children.start + position
+            };
+            board.make_move(move_made);
+            NodeRef::new(&tree.read(), child_node).add_virtual_loss(virtual_loss);
+            nodes.push(child_node);
+            break Self::simulate(tree, algo, child_node, Some(move_made), &board);
+        };
+        Self::backpropagate(tree, algo, &nodes, leaf, virtual_loss, false, false);
+        drop(iteration);
+        if tree.needs_pruning() {
+            tree.prune();
+        }
+    }
+
+    /// Result of the finished game on `board`.
+    fn terminal(tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>, board: &Board) -> Rollout {
+        let turn = board.turn();
+        let margin = board
+            .terminal_score()
+            .map(|score| if turn == Player::P1 { score } else { -score });
+        if margin.is_some() {
+            tree.scored.store(true, Ordering::Relaxed);
+        }
+        if tree.players > 2 {
+            let rewards = board.rewards();
+            return Rollout {
+                score: rewards[turn.index()],
+                margin,
+                rewards,
+            };
+        }
+        Rollout {
+            score: Proof::from_game_state(board.game_state(), turn)
+                .score()
+                .unwrap(),
+            margin,
+            rewards: vec![],
         }
-        let mut last_score = rollout_score;
-        for node in nodes.iter().rev() {
-            let mut node = node.lock().unwrap();
+    }
+
+    /// Adds the result of a simulation to every node on the path and removes their virtual
+    /// losses. Two player results are flipped whenever the player to move changes, while games
+    /// with more players add the reward of every player, so each node picks the child best for
+    /// its own player (max^n). `proven` and `bounded` tell whether the leaf is solved, in which
+    /// case its ancestors may be as well.
+    fn backpropagate(
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        nodes: &[usize],
+        rollout: Rollout,
+        virtual_loss: f32,
+        mut proven: bool,
+        mut bounded: bool,
+    ) {
+        let Rollout {
+            score: mut last_score,
+            margin: mut last_margin,
+            rewards,
+        } = rollout;
+        tree.paths.add(nodes.len() - 1);
+        let tree_nodes = tree.read();
+        for (depth, &node) in nodes.iter().enumerate().rev() {
+            //Nodes may have been moved by widening since the path was selected
+            let parent = depth
+                .checked_sub(1)
+                .map(|parent| Tree::resolve(&tree_nodes, nodes[parent]));
+            let node = Tree::resolve(&tree_nodes, node);
+            let edge = parent
+                .and_then(|parent| {
+                    tree_nodes[parent]
+                        .children
+                        .clone()
+                        .find(|&edge| Tree::resolve(&tree_nodes, edge) == node)
+                })
+                .unwrap_or(node);
+            let node = NodeRef::reached_from(&tree_nodes, node, parent, edge);
+            if depth > 0 {
+                node.add_virtual_loss(-virtual_loss);
+            }
+            if !rewards.is_empty() {
+                last_score = rewards[node.board().turn().index()];
+                for (sum, reward) in node.rewards.iter().zip(&rewards) {
+                    sum.add(*reward);
+                }
+            }
             for algorithm in &algo.backprop_algorithms {
-                algorithm.lock().unwrap().backprop(&mut node, last_score);
+                algorithm.lock().unwrap().backprop(node, last_score);
+            }
+            node.score.add(last_score);
+            node.visits.add(1f32);
+            if let Some(margin) = last_margin {
+                node.margin.add(margin);
+            }
+            if node.flipped() {
+                last_score = 1f32 - last_score;
+                last_margin = last_margin.map(|margin| -margin);
+            }
+            if bounded && !node.children.is_empty() {
+                bounded = Self::backup_bounds(node);
+                proven |= node.proof() != Proof::Unknown;
+            }
+            if proven && node.proof() == Proof::Unknown {
+                let proof = Self::backup_proof(node);
+                node.proof.set(proof);
+                proven = proof != Proof::Unknown;
+            }
+        }
+    }
+
+    /// Negamax over the score bounds of the children, or their expectation at chance nodes. A
+    /// node whose bounds meet is proven by the sign of its score. Returns whether the bounds
+    /// changed.
+    fn backup_bounds(node: NodeRef<Board, Params, Move, DATA_REGISTERS>) -> bool {
+        let (mut pessimistic, mut optimistic) = if node.chance {
+            (0f32, 0f32)
+        } else {
+            (f32::NEG_INFINITY, f32::NEG_INFINITY)
+        };
+        for (_, child) in node.children() {
+            let (child_pessimistic, child_optimistic) = child.parent_bounds();
+            if node.chance {
+                pessimistic += child.probability() * child_pessimistic;
+                optimistic += child.probability() * child_optimistic;
+            } else {
+                pessimistic = pessimistic.max(child_pessimistic);
+                optimistic = optimistic.max(child_optimistic);
+            }
+        }
+        if node.children.len() < node.moves.len() {
+            optimistic = f32::INFINITY;
+        }
+        if pessimistic == node.pessimistic.get() && optimistic == node.optimistic.get() {
+            return false;
+        }
+        node.pessimistic.set(pessimistic);
+        node.optimistic.set(optimistic);
+        if node.bounded() && node.proof() == Proof::Unknown {
+            node.proof.set(Proof::from_score(pessimistic));
+        }
+        true
+    }
+
+    /// Plays out `board`, the position of `node`. Simulations without rewards count as a draw
+    /// in games with more than two players.
+    fn simulate(
+        tree: &Tree<Board, Params, Move, { DATA_REGISTERS }>,
+        algo: &BarracudaAlgorithms<Board, Params, Move, { DATA_REGISTERS }>,
+        node: usize,
+        last_move: Option<Move>,
+        board: &Board,
+    ) -> Rollout {
+        let (rollout, margin, mut rewards) = {
+            let mut simulation = algo.simulation.lock().unwrap();
+            let rollout = simulation.simulate(board);
+            (rollout, simulation.margin(), simulation.rewards())
+        };
+        let turn = board.turn();
+        let (rollout_score, margin) = if turn == Player::P1 {
+            (rollout, margin)
+        } else {
+            (1f32 - rollout, margin.map(|margin| -margin))
+        };
+        let rollout_score = if tree.players > 2 {
+            if rewards.is_empty() {
+                rewards = vec![1f32 / tree.players as f32; tree.players];
+            }
+            rewards[turn.index()]
+        } else {
+            rollout_score
+        };
+        if margin.is_some() {
+            tree.scored.store(true, Ordering::Relaxed);
+        }
+        if let Some(last_move) = last_move {
+            let tree_nodes = tree.read();
+            let node = Tree::resolve(&tree_nodes, node);
+            for algorithm in &algo.simulation_algorithms {
+                algorithm.lock().unwrap().set_node(
+                    NodeRef::new(&tree_nodes, node),
+                    &last_move,
+                    rollout_score,
+                )
+            }
+        }
+        Rollout {
+            score: rollout_score,
+            margin,
+            rewards,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::barracuda::b_core::algorithms::extensions::t_table::TranspositionTable;
+    use crate::barracuda::b_core::algorithms::ucb::formula::Formula;
+    use crate::barracuda::b_core::algorithms::ucb::value::Value;
+    use crate::barracuda::b_core::components::policy::Policy;
+    use crate::barracuda::b_core::params::BarracudaUcbParams;
+    use crate::barracuda::b_runner::tests::algorithms;
+    use crate::barracuda::mcts::{
+        BarracudaAlgorithms, BudgetPolicy, Node, NodeBudget, NodeRef, Outcome, ProgressiveWidening,
+        Tree, WideningOrder, PRUNE_TARGET, ROOT,
+    };
+    use crate::barracuda::traits::{BarracudaAlgorithm, BarracudaBoard};
+    use crate::cf::cf::{CfParams, ConnectFour};
+    use crate::pig::{PigBoard, PigMove, PigParams};
+    use crate::tictactoe::ttt::{Square, TicTacToeBoard, TicTacToeParams};
+    use std::sync::atomic::Ordering;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn chance_outcomes_keep_their_probability_in_a_dag() {
+        let mut tree = Tree::new(PigBoard::new(PigParams::new(10)));
+        tree.set_transpositions(true);
+        let algo = algorithms();
+        for _ in 0..3000 {
+            Node::search(&tree, &algo, 0f32);
+        }
+        assert!(tree.transpositions() > 0);
+        let nodes = tree.read();
+        let mut chance_nodes = 0;
+        for node in (0..nodes.len()).map(|id| NodeRef::new(&nodes, id)) {
+            if !node.chance || node.transposition.is_some() {
+                continue;
+            }
+            chance_nodes += 1;
+            //Outcomes linking to a node first reached by a decision still have their own odds
+            let outcomes = node.board().chance_outcomes();
+            for ((mv, child), (outcome, probability)) in node.children().zip(outcomes) {
+                assert_eq!(mv, outcome);
+                assert_eq!(child.probability(), probability);
+            }
+        }
+        assert!(chance_nodes > 0);
+    }
+
+    #[test]
+    fn repeated_positions_are_only_visited_once_per_iteration() {
+        let mut tree = Tree::new(PigBoard::new(PigParams::new(10)));
+        tree.set_transpositions(true);
+        let algo = algorithms();
+        let mut repeated = 0;
+        for iteration in 1..=3000 {
+            let descent = Node::descend(&tree, &algo, 0f32);
+            let mut nodes = descent.nodes.clone();
+            nodes.sort_unstable();
+            nodes.dedup();
+            assert_eq!(nodes.len(), descent.nodes.len());
+            let rollout = match descent.leaf {
+                Outcome::Known(rollout) => rollout,
+                Outcome::Pending(node, last_move, board) => {
+                    //Holding or rolling a one on both turns comes back to the same position
+                    if !tree.read()[node].children.is_empty() {
+                        repeated += 1;
+                    }
+                    Node::simulate(&tree, &algo, node, last_move, &board)
+                }
+            };
+            Node::backpropagate(
+                &tree,
+                &algo,
+                &descent.nodes,
+                rollout,
+                0f32,
+                descent.proven,
+                descent.bounded,
+            );
+            assert_eq!(tree.read()[ROOT].visits(), iteration);
+        }
+        assert!(repeated > 0);
+    }
+
+    #[test]
+    fn virtual_loss_is_a_loss_for_the_player_choosing_the_move() {
+        let tree = Tree::new(PigBoard::new(PigParams::new(10)));
+        let algo = algorithms();
+        for _ in 0..100 {
+            Node::search(&tree, &algo, 0f32);
+        }
+        let nodes = tree.read();
+        let root = NodeRef::new(&nodes, ROOT);
+        //Rolling keeps the turn, holding passes it
+        for (mv, child) in root.children() {
+            assert_eq!(child.flipped(), mv == PigMove::Hold);
+            let mut value = Value::new();
+            let mut win = || {
+                BarracudaUcbParams::<_, _, _, 0>::set_node(&mut value, child, &mv);
+                value.get()
+            };
+            let before = win();
+            child.add_virtual_loss(1f32);
+            assert!(win() < before);
+            child.add_virtual_loss(-1f32);
+            assert_eq!(win(), before);
+        }
+    }
+
+    #[test]
+    fn prune_counts_children_moved_by_widening() {
+        let mut tree = Tree::new(ConnectFour::new(CfParams::default()));
+        let widening = ProgressiveWidening::new(1.0, 0.5, WideningOrder::Prior);
+        tree.set_progressive_widening(Some(widening));
+        let algo = algorithms();
+        for _ in 0..3000 {
+            Node::search(&tree, &algo, 0f32);
+        }
+        {
+            let nodes = tree.read();
+            //Some children were moved behind their own children
+            assert!((0..nodes.len()).any(|id| nodes[id].children.start < id
+                && !nodes[id].children.is_empty()
+                && nodes[id].parent.is_some()));
+            let reachable = Tree::subtree_costs(&nodes, |_| 1)[ROOT];
+            let placeholders = nodes
+                .iter()
+                .filter(|node| node.parent.is_none() && node.transposition.is_some())
+                .count();
+            assert_eq!(reachable + placeholders, nodes.len());
+        }
+        let size = tree.len();
+        tree.set_budget(Some((NodeBudget::Nodes(size), BudgetPolicy::Prune)));
+        tree.budget.full.store(true, Ordering::Relaxed);
+        tree.prune();
+        assert!(tree.len() <= (size as f32 * PRUNE_TARGET) as usize);
+    }
+
+    #[test]
+    fn boards_are_only_kept_within_the_budget() {
+        let mut tree = Tree::new(ConnectFour::new(CfParams::default()));
+        let budget = 100_000;
+        tree.set_budget(Some((
+            NodeBudget::Bytes(budget),
+            BudgetPolicy::StopExpanding,
+        )));
+        //The table looks at the board of every child it scores
+        let t_table = Arc::new(Mutex::new(TranspositionTable::new()));
+        let mut algo = algorithms();
+        algo.ucb_algorithms.push(t_table.clone());
+        algo.backprop_algorithms.push(t_table);
+        for _ in 0..3000 {
+            Node::search(&tree, &algo, 0f32);
+        }
+        let memory = |nodes: &[Node<_, _, _, 0>]| nodes.iter().map(Node::memory).sum::<usize>();
+        let nodes = tree.read();
+        assert_eq!(tree.memory(), memory(&nodes));
+        assert!(tree.memory() <= budget);
+        for id in 1..nodes.len() {
+            let node = NodeRef::new(&nodes, id);
+            let parent = node.parent().unwrap();
+            let mut board = parent.board().into_owned();
+            board.make_move(parent.moves[id - parent.children.start]);
+            assert!(*node.board() == board);
+        }
+        //Boards computed by the loop above weren't kept
+        assert_eq!(tree.memory(), memory(&nodes));
+    }
+
+    /// Prefers the later moves, so a square gets a different prior depending on the position it
+    /// is played in.
+    struct LaterMoves;
+
+    impl BarracudaAlgorithm for LaterMoves {}
+
+    impl Policy<TicTacToeBoard, TicTacToeParams, Square> for LaterMoves {
+        fn pick(&mut self, _: &TicTacToeBoard, moves: &[Square]) -> usize {
+            moves.len() - 1
+        }
+
+        fn priors(&mut self, _: &TicTacToeBoard, moves: &[Square]) -> Vec<f32> {
+            let total = (moves.len() * (moves.len() + 1) / 2) as f32;
+            (1..=moves.len())
+                .map(|index| index as f32 / total)
+                .collect()
+        }
+    }
+
+    #[test]
+    fn links_keep_the_prior_of_their_own_parent() {
+        let mut tree = Tree::new(TicTacToeBoard::new(TicTacToeParams));
+        tree.set_transpositions(true);
+        let algo = BarracudaAlgorithms {
+            policy: Arc::new(Mutex::new(LaterMoves)),
+            ..algorithms()
+        };
+        for _ in 0..3000 {
+            Node::search(&tree, &algo, 0f32);
+        }
+        assert!(tree.transpositions() > 0);
+        let nodes = tree.read();
+        for node in (0..nodes.len()).map(|id| NodeRef::new(&nodes, id)) {
+            if node.children.is_empty() {
+                continue;
+            }
+            let priors = LaterMoves.priors(&node.board(), &node.moves);
+            for ((_, child), prior) in node.children().zip(priors) {
+                assert_eq!(child.prior(), prior);
             }
-            node.score += last_score;
-            node.visits += 1f32;
-            last_score = 1f32 - last_score;
         }
     }
 }
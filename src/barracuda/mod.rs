@@ -1,5 +1,8 @@
+pub mod atomic;
 pub mod b_core;
 pub mod b_runner;
 pub mod debugger;
+pub mod export;
 pub mod mcts;
+pub mod time_manager;
 pub mod traits;
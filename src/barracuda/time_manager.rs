@@ -0,0 +1,79 @@
+/// Clock of the player to move, in seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Clock {
+    pub remaining: f32,
+    /// Added to the clock after every move.
+    pub increment: f32,
+    /// Moves until the clock gets more time, if the time control has such periods.
+    pub moves_to_go: Option<u32>,
+}
+
+impl Clock {
+    pub fn new(remaining: f32, increment: f32, moves_to_go: Option<u32>) -> Self {
+        Self {
+            remaining,
+            increment,
+            moves_to_go,
+        }
+    }
+}
+
+/// Splits a `Clock` over the moves left in the game. A search aims for its share of the clock,
+/// goes on past it while the most visited and the best scoring root moves differ, and stops early
+/// once the most visited move can't be overtaken in the time left, see `SearchLimits::clock`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TimeManager {
+    /// Moves the clock has to last when it doesn't say.
+    pub moves_to_go: u32,
+    /// How many times its share of the clock a search may use when the root moves disagree.
+    pub extension: f32,
+    /// Seconds kept on the clock for the time it takes to play a move.
+    pub overhead: f32,
+}
+
+impl TimeManager {
+    pub fn new(moves_to_go: u32, extension: f32, overhead: f32) -> Self {
+        Self {
+            moves_to_go,
+            extension,
+            overhead,
+        }
+    }
+
+    /// Seconds the search of the next move aims for and may extend to.
+    pub fn budget(&self, clock: &Clock) -> (f32, f32) {
+        let available = (clock.remaining - self.overhead).max(0f32);
+        let moves = clock.moves_to_go.unwrap_or(self.moves_to_go).max(1);
+        let share = available / moves as f32 + clock.increment;
+        (
+            share.min(available),
+            (share * self.extension).min(available),
+        )
+    }
+}
+
+impl Default for TimeManager {
+    fn default() -> Self {
+        Self::new(30, 2f32, 0.05f32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::barracuda::time_manager::{Clock, TimeManager};
+
+    #[test]
+    fn time_manager_splits_the_clock() {
+        let time_manager = TimeManager::new(30, 2.0, 0.0);
+        let (target, maximum) = time_manager.budget(&Clock::new(60.0, 1.0, None));
+        assert!((target - 3.0).abs() < 1e-4 && (maximum - 6.0).abs() < 1e-4);
+        //The last move before the clock gets more time may use all of it
+        let (target, maximum) = time_manager.budget(&Clock::new(60.0, 1.0, Some(1)));
+        assert_eq!((target, maximum), (60.0, 60.0));
+        //The overhead is kept whatever the clock says
+        let time_manager = TimeManager::new(30, 2.0, 1.0);
+        assert_eq!(time_manager.budget(&Clock::new(0.5, 0.0, None)), (0.0, 0.0));
+        let (target, _) = time_manager.budget(&Clock::new(31.0, 0.0, None));
+        assert!((target - 1.0).abs() < 1e-4);
+    }
+}
@@ -2,11 +2,13 @@ use std::hash::Hash;
 
 pub trait BarracudaAlgorithm: Send {}
 
-pub trait BarracudaMove: Copy + Hash + PartialEq + Eq + Send + 'static {}
+pub trait BarracudaMove: Copy + Hash + PartialEq + Eq + Send + Sync + 'static {}
 
-pub trait BarracudaParams: Send + Default + 'static {}
+pub trait BarracudaParams: Send + Sync + Default + 'static {}
 
-pub trait BarracudaBoard<Params, Move>: Clone + Hash + PartialEq + Eq + Send + 'static {
+pub trait BarracudaBoard<Params, Move>:
+    Clone + Hash + PartialEq + Eq + Send + Sync + 'static
+{
     fn new(params: Params) -> Self;
 
     fn make_move(&mut self, mv: Move);
@@ -17,13 +19,84 @@ pub trait BarracudaBoard<Params, Move>: Clone + Hash + PartialEq + Eq + Send + '
 
     fn turn(&self) -> Player;
 
+    /// Number of players taking turns, at most six. Games with more than two players are
+    /// searched with a reward per player, see `rewards`.
+    fn players(&self) -> usize {
+        2
+    }
+
+    /// Reward of every player, indexed by `Player::index`, once the game has ended. The winner
+    /// gets 1 and a draw is shared. Only used by games with more than two players.
+    fn rewards(&self) -> Vec<f32> {
+        let players = self.players();
+        match self.game_state() {
+            GameState::End(Some(winner)) => (0..players)
+                .map(|player| if player == winner.index() { 1f32 } else { 0f32 })
+                .collect(),
+            _ => vec![1f32 / players as f32; players],
+        }
+    }
+
     fn move_to_str(&self, mv: Move) -> String;
+
+    /// Outcomes of a chance event with their probabilities when it is nature's turn, each
+    /// applied with `make_move`. Empty while a player is to move. `turn` still names the player
+    /// whose perspective the position is evaluated from.
+    fn chance_outcomes(&self) -> Vec<(Move, f32)> {
+        vec![]
+    }
+
+    /// Samples a full position consistent with what `observer` knows, for games with hidden
+    /// information. Only used by information set searches.
+    fn determinize(&self, _observer: Player) -> Self {
+        self.clone()
+    }
+
+    /// Final score margin of P1 over P2 once the game has ended, for games where the margin
+    /// matters and not only the winner.
+    fn terminal_score(&self) -> Option<f32> {
+        None
+    }
+
+    /// Bytes the board owns on the heap, used to estimate the memory of the search tree.
+    fn heap_size(&self) -> usize {
+        0
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
 pub enum Player {
     P1,
     P2,
+    P3,
+    P4,
+    P5,
+    P6,
+}
+
+impl Player {
+    const ALL: [Player; 6] = [
+        Player::P1,
+        Player::P2,
+        Player::P3,
+        Player::P4,
+        Player::P5,
+        Player::P6,
+    ];
+
+    /// Position in the turn order, starting at 0 for P1.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    pub fn from_index(index: usize) -> Self {
+        Self::ALL[index]
+    }
+
+    /// Player moving after this one in a game of `players` players.
+    pub fn next(self, players: usize) -> Self {
+        Self::from_index((self.index() + 1) % players)
+    }
 }
 
 #[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
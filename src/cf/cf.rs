@@ -1,4 +1,5 @@
 use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams, GameState, Player};
+use std::mem::size_of;
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct ConnectFour {
@@ -123,10 +124,7 @@ impl BarracudaBoard<CfParams, usize> for ConnectFour {
             panic!();
         }
 
-        self.turn = match self.turn {
-            Player::P1 => Player::P2,
-            Player::P2 => Player::P1,
-        };
+        self.turn = self.turn.next(2);
     }
 
     fn get_moves(&self) -> Vec<usize> {
@@ -157,4 +155,13 @@ impl BarracudaBoard<CfParams, usize> for ConnectFour {
     fn move_to_str(&self, mv: usize) -> String {
         format!("{}", mv)
     }
+
+    fn heap_size(&self) -> usize {
+        self.board.capacity() * size_of::<Vec<Option<Player>>>()
+            + self
+                .board
+                .iter()
+                .map(|column| column.capacity() * size_of::<Option<Player>>())
+                .sum::<usize>()
+    }
 }
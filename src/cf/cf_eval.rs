@@ -19,7 +19,7 @@ impl StaticEval<ConnectFour, CfParams, usize> for CfEval {
                         Player::P1 => {
                             eval += col_score;
                         }
-                        Player::P2 => {
+                        _ => {
                             eval -= col_score;
                         }
                     }
@@ -28,7 +28,7 @@ impl StaticEval<ConnectFour, CfParams, usize> for CfEval {
         }
         match board.turn() {
             Player::P1 => eval,
-            Player::P2 => -eval,
+            _ => -eval,
         }
     }
 }
@@ -1,27 +1,76 @@
-use crate::barracuda::b_runner::BarracudaRunner;
+use crate::barracuda::b_runner::{BarracudaRunner, MoveSelection, SearchHandle, SearchLimits};
 use crate::cf::cf::{CfParams, ConnectFour};
 use crate::cli::cli::CommandOut::{Error, Success, Warning};
+use crate::kuhn::{KuhnBoard, KuhnMove, KuhnParams};
+use crate::pig::{PigBoard, PigMove, PigParams};
 use crate::tictactoe::ttt::{Square, TicTacToeBoard, TicTacToeParams};
 
 use crate::barracuda::b_core::algorithms::policy::uniform::Uniform;
 use crate::barracuda::b_core::algorithms::simulate::random_playout::RandomPlayout;
 use crate::barracuda::b_core::algorithms::ucb::exploration::Exploration;
-use crate::barracuda::b_core::algorithms::ucb::formula::Add;
+use crate::barracuda::b_core::algorithms::ucb::expression::FormulaRegistry;
 use crate::barracuda::b_core::algorithms::ucb::value::Value;
 use crate::barracuda::b_core::components::ucb::Ucb;
 use crate::barracuda::debugger::BarracudaDebug;
+use crate::barracuda::export::{TreeExport, TreeFormat};
 use crate::barracuda::mcts::BarracudaAlgorithms;
 use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams};
-use crate::cli::cli::Runner::{Cf, Ttt};
+use crate::cli::cli::Runner::{Cf, Kuhn, Pig, Ttt};
+use std::fmt::Debug;
+use std::fs::File;
+use std::io::{BufWriter, Write};
 use std::sync::{Arc, Mutex};
-use std::thread::JoinHandle;
 
 enum Runner {
-    Cf(Arc<Mutex<BarracudaRunner<ConnectFour, CfParams, usize, 0>>>),
-    Ttt(Arc<Mutex<BarracudaRunner<TicTacToeBoard, TicTacToeParams, Square, 0>>>),
+    Cf(Session<ConnectFour, CfParams, usize>),
+    Ttt(Session<TicTacToeBoard, TicTacToeParams, Square>),
+    Pig(Session<PigBoard, PigParams, PigMove>),
+    Kuhn(Session<KuhnBoard, KuhnParams, KuhnMove>),
     Deactivated,
 }
 
+/// A runner, or the search running on it in the background.
+struct Session<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove> {
+    runner: Option<BarracudaRunner<Board, Params, Move, 0>>,
+    search: Option<SearchHandle<Board, Params, Move, 0>>,
+}
+
+impl<Board: BarracudaBoard<Params, Move>, Params: BarracudaParams, Move: BarracudaMove + Debug>
+    Session<Board, Params, Move>
+{
+    fn new(runner: BarracudaRunner<Board, Params, Move, 0>) -> Self {
+        Self {
+            runner: Some(runner),
+            search: None,
+        }
+    }
+
+    /// Searches for `think_time` seconds in the background, once the previous search is over.
+    fn analyze(&mut self, think_time: f32) {
+        self.finish(false);
+        let runner = self.runner.take().unwrap();
+        self.search = Some(runner.start::<BarracudaDebug>(SearchLimits::time(think_time)));
+    }
+
+    /// Writes the tree to `path`, once the search in the background is over.
+    fn dump(&mut self, path: &str, export: &TreeExport) -> std::io::Result<()> {
+        let runner = self.finish(false);
+        let mut out = BufWriter::new(File::create(path)?);
+        runner.export_tree(export, &mut out)?;
+        out.flush()
+    }
+
+    /// Waits for the search in the background, or stops it, and prints its best move.
+    fn finish(&mut self, stop: bool) -> &mut BarracudaRunner<Board, Params, Move, 0> {
+        if let Some(search) = self.search.take() {
+            let (runner, _) = if stop { search.stop() } else { search.wait() };
+            println!("{:?}", runner.best_move());
+            self.runner = Some(runner);
+        }
+        self.runner.as_mut().unwrap()
+    }
+}
+
 pub enum CommandOut<T, U, V> {
     Success(T),
     Warning((T, U)),
@@ -32,28 +81,23 @@ enum Cmd<'a> {
     Analyze(Vec<&'a str>),
     Mode(Vec<&'a str>),
     MakeMove(Vec<&'a str>),
+    Select(Vec<&'a str>),
+    Dump(Vec<&'a str>),
+    Stop,
 }
 
 pub struct Cli {
     runner: Runner,
-    current: Option<JoinHandle<()>>,
 }
 
 impl Cli {
     pub fn new() -> Self {
         Self {
             runner: Runner::Deactivated,
-            current: None,
         }
     }
 
     pub fn parse(&mut self, input: String) -> CommandOut<bool, String, String> {
-        match self.current.take() {
-            None => {}
-            Some(handle) => {
-                handle.join().unwrap();
-            }
-        }
         let input = input.split_ascii_whitespace().collect::<Vec<_>>();
         if input.is_empty() {
             return Error("No commands have been given".to_string());
@@ -75,10 +119,27 @@ impl Cli {
                     return Error("No parameters have been given to Move".to_string());
                 }
             }
+            "stop" => Cmd::Stop,
+            "dump" => {
+                if input.len() > 1 {
+                    Cmd::Dump(input[1..].to_vec())
+                } else {
+                    return Error("No parameters have been given to Dump".to_string());
+                }
+            }
+            "select" => {
+                if input.len() > 1 {
+                    Cmd::Select(input[1..].to_vec())
+                } else {
+                    return Error("No parameters have been given to Select".to_string());
+                }
+            }
             _ => {
                 return Error("Unrecognized command".to_string());
             }
         };
+        //Commands other than stop wait for the search running in the background
+        self.finish(matches!(cmd, Cmd::Stop));
 
         match cmd {
             Cmd::Analyze(params) => {
@@ -92,20 +153,10 @@ impl Cli {
                     }
                 };
                 match &mut self.runner {
-                    Runner::Cf(cf_runner) => {
-                        let runner = cf_runner.clone();
-                        self.current = Some(std::thread::spawn(move || {
-                            runner.lock().unwrap().search::<BarracudaDebug>(think_time);
-                            println!("{:?}", runner.lock().unwrap().best_move());
-                        }));
-                    }
-                    Runner::Ttt(ttt_runner) => {
-                        let runner = ttt_runner.clone();
-                        self.current = Some(std::thread::spawn(move || {
-                            runner.lock().unwrap().search::<BarracudaDebug>(think_time);
-                            println!("{:?}", runner.lock().unwrap().best_move());
-                        }));
-                    }
+                    Runner::Cf(session) => session.analyze(think_time),
+                    Runner::Ttt(session) => session.analyze(think_time),
+                    Runner::Pig(session) => session.analyze(think_time),
+                    Runner::Kuhn(session) => session.analyze(think_time),
                     Runner::Deactivated => {
                         return Error("No game has been activated".to_string());
                     }
@@ -122,18 +173,26 @@ impl Cli {
             }
             Cmd::Mode(mode) => match mode[0] {
                 "cf" => {
-                    self.runner = Cf(Arc::new(Mutex::new(Self::new_runner::<
-                        ConnectFour,
-                        CfParams,
-                        usize,
-                    >())));
+                    self.runner = Cf(Session::new(
+                        Self::new_runner::<ConnectFour, CfParams, usize>(),
+                    ));
                 }
                 "ttt" => {
-                    self.runner = Ttt(Arc::new(Mutex::new(Self::new_runner::<
+                    self.runner = Ttt(Session::new(Self::new_runner::<
                         TicTacToeBoard,
                         TicTacToeParams,
                         Square,
-                    >())));
+                    >()));
+                }
+                "pig" => {
+                    self.runner = Pig(Session::new(
+                        Self::new_runner::<PigBoard, PigParams, PigMove>(),
+                    ));
+                }
+                "kuhn" => {
+                    let mut runner = Self::new_runner::<KuhnBoard, KuhnParams, KuhnMove>();
+                    runner.set_information_set_search(true);
+                    self.runner = Kuhn(Session::new(runner));
                 }
                 _ => {
                     return Error("Unrecognized game".to_string());
@@ -141,10 +200,12 @@ impl Cli {
             },
             Cmd::MakeMove(params) => {
                 match &self.runner {
-                    Cf(_runner) => {
+                    Cf(_session) => {
                         //runner.clone().lock().unwrap().make_move(params[0])
                     }
-                    Ttt(_runner) => {}
+                    Ttt(_session) => {}
+                    Pig(_session) => {}
+                    Kuhn(_session) => {}
                     Runner::Deactivated => {}
                 }
                 return if params.len() > 1 {
@@ -157,26 +218,144 @@ impl Cli {
                     Success(true)
                 };
             }
+            Cmd::Select(params) => {
+                let selection = match Self::parse_selection(&params) {
+                    Ok(selection) => selection,
+                    Err(error) => {
+                        return Error(error);
+                    }
+                };
+                match &mut self.runner {
+                    Cf(session) => session.finish(false).set_move_selection(selection),
+                    Ttt(session) => session.finish(false).set_move_selection(selection),
+                    Pig(session) => session.finish(false).set_move_selection(selection),
+                    Kuhn(session) => session.finish(false).set_move_selection(selection),
+                    Runner::Deactivated => {
+                        return Error("No game has been activated".to_string());
+                    }
+                }
+                let parameters = match selection {
+                    MoveSelection::Secure(_) | MoveSelection::Temperature(_) => 2,
+                    _ => 1,
+                };
+                return if params.len() > parameters {
+                    Warning((
+                        true,
+                        "Too many parameters have been given, the rest have been ignored"
+                            .to_string(),
+                    ))
+                } else {
+                    Success(true)
+                };
+            }
+            Cmd::Dump(params) => {
+                let export = match Self::parse_export(&params[1..]) {
+                    Ok(export) => export,
+                    Err(error) => {
+                        return Error(error);
+                    }
+                };
+                let dumped = match &mut self.runner {
+                    Cf(session) => session.dump(params[0], &export),
+                    Ttt(session) => session.dump(params[0], &export),
+                    Pig(session) => session.dump(params[0], &export),
+                    Kuhn(session) => session.dump(params[0], &export),
+                    Runner::Deactivated => {
+                        return Error("No game has been activated".to_string());
+                    }
+                };
+                if let Err(error) = dumped {
+                    return Error(error.to_string());
+                }
+                return if params.len() > 4 {
+                    Warning((
+                        true,
+                        "Too many parameters have been given, the rest have been ignored"
+                            .to_string(),
+                    ))
+                } else {
+                    Success(true)
+                };
+            }
+            Cmd::Stop => {}
         }
 
         Success(true)
     }
 
+    /// Waits for the search running in the background, or stops it.
+    fn finish(&mut self, stop: bool) {
+        match &mut self.runner {
+            Cf(session) => {
+                session.finish(stop);
+            }
+            Ttt(session) => {
+                session.finish(stop);
+            }
+            Pig(session) => {
+                session.finish(stop);
+            }
+            Kuhn(session) => {
+                session.finish(stop);
+            }
+            Runner::Deactivated => {}
+        }
+    }
+
+    /// `max`, `robust`, `max-robust`, `secure [c]` or `temperature [t]`, `c` and `t` being 1 by
+    /// default.
+    fn parse_selection(params: &[&str]) -> Result<MoveSelection, String> {
+        //Only the strategies with a parameter read the next argument
+        let value = || match params.get(1) {
+            Some(value) => value.parse::<f32>().map_err(|error| error.to_string()),
+            None => Ok(1f32),
+        };
+        match params[0] {
+            "max" => Ok(MoveSelection::Max),
+            "robust" => Ok(MoveSelection::Robust),
+            "max-robust" => Ok(MoveSelection::MaxRobust),
+            "secure" => Ok(MoveSelection::Secure(value()?)),
+            "temperature" => Ok(MoveSelection::Temperature(value()?)),
+            _ => Err("Unrecognized move selection".to_string()),
+        }
+    }
+
+    /// `[dot|json] [depth] [min visits]` after the file of a dump, see `TreeExport::default` for
+    /// the missing ones.
+    fn parse_export(params: &[&str]) -> Result<TreeExport, String> {
+        let mut export = TreeExport::default();
+        if let Some(format) = params.first() {
+            export.format = match *format {
+                "dot" => TreeFormat::Dot,
+                "json" => TreeFormat::Json,
+                _ => return Err("Unrecognized tree format".to_string()),
+            };
+        }
+        if let Some(depth) = params.get(1) {
+            export.max_depth = depth.parse::<usize>().map_err(|error| error.to_string())?;
+        }
+        if let Some(min_visits) = params.get(2) {
+            export.min_visits = min_visits
+                .parse::<u32>()
+                .map_err(|error| error.to_string())?;
+        }
+        Ok(export)
+    }
+
     fn new_runner<
         Board: BarracudaBoard<Params, Move>,
         Params: BarracudaParams,
         Move: BarracudaMove,
     >() -> BarracudaRunner<Board, Params, Move, 0> {
-        let value = Arc::new(Mutex::new(Value::new()));
-        let exploration = Arc::new(Mutex::new(Exploration::new(1.414)));
+        let mut registry = FormulaRegistry::new();
+        registry.register("value", Arc::new(Mutex::new(Value::new())));
+        registry.register("explore", Arc::new(Mutex::new(Exploration::new(1f32))));
+        let expression = registry.parse("value + 1.414 * explore").unwrap();
 
         let runner = BarracudaRunner::<Board, Params, Move, 0>::new(
             BarracudaAlgorithms {
-                ucb_algorithms: vec![value.clone(), exploration.clone()],
-                ucb: Ucb::new(Arc::new(Mutex::new(Add::new(vec![
-                    value.clone(),
-                    exploration.clone(),
-                ])))),
+                ucb_algorithms: expression.ucb_algorithms,
+                ucb: Ucb::new(expression.formula),
                 policy: Arc::new(Mutex::new(Uniform::new())),
                 simulation_algorithms: vec![],
                 simulation: Arc::new(Mutex::new(RandomPlayout::new(
@@ -0,0 +1,150 @@
+use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams, GameState, Player};
+use rand::Rng;
+
+const CARDS: u8 = 3;
+
+#[derive(Default)]
+pub struct KuhnParams;
+
+impl BarracudaParams for KuhnParams {}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum KuhnMove {
+    /// Cards dealt to P1 and P2, 0 being the jack and 2 the king.
+    Deal(u8, u8),
+    Check,
+    Bet,
+    Call,
+    Fold,
+}
+
+impl BarracudaMove for KuhnMove {}
+
+/// Both players ante a chip and get one of three cards. Players may check or bet a chip, a bet
+/// has to be called or folded, and the higher card wins the pot at showdown.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct KuhnBoard {
+    cards: Option<[u8; 2]>,
+    pot: [u8; 2],
+    turn: Player,
+    checks: u8,
+    bet: bool,
+    showdown: bool,
+    folded: Option<Player>,
+}
+
+impl KuhnBoard {
+    fn switch_turn(&mut self) {
+        self.turn = self.turn.next(2);
+    }
+}
+
+impl BarracudaBoard<KuhnParams, KuhnMove> for KuhnBoard {
+    fn new(_: KuhnParams) -> Self {
+        Self {
+            cards: None,
+            pot: [1; 2],
+            turn: Player::P1,
+            checks: 0,
+            bet: false,
+            showdown: false,
+            folded: None,
+        }
+    }
+
+    fn make_move(&mut self, mv: KuhnMove) {
+        match mv {
+            KuhnMove::Deal(p1, p2) => self.cards = Some([p1, p2]),
+            KuhnMove::Check => {
+                self.checks += 1;
+                self.showdown = self.checks == 2;
+                self.switch_turn();
+            }
+            KuhnMove::Bet => {
+                self.pot[self.turn.index()] += 1;
+                self.bet = true;
+                self.switch_turn();
+            }
+            KuhnMove::Call => {
+                self.pot[self.turn.index()] += 1;
+                self.showdown = true;
+                self.switch_turn();
+            }
+            KuhnMove::Fold => {
+                self.folded = Some(self.turn);
+                self.switch_turn();
+            }
+        }
+    }
+
+    fn get_moves(&self) -> Vec<KuhnMove> {
+        if self.cards.is_none() || !matches!(self.game_state(), GameState::Ongoing) {
+            vec![]
+        } else if self.bet {
+            vec![KuhnMove::Call, KuhnMove::Fold]
+        } else {
+            vec![KuhnMove::Check, KuhnMove::Bet]
+        }
+    }
+
+    fn game_state(&self) -> GameState {
+        match (self.folded, self.cards) {
+            (Some(folded), _) => GameState::End(Some(folded.next(2))),
+            (None, Some([p1, p2])) if self.showdown => {
+                GameState::End(Some(if p1 > p2 { Player::P1 } else { Player::P2 }))
+            }
+            _ => GameState::Ongoing,
+        }
+    }
+
+    fn turn(&self) -> Player {
+        self.turn
+    }
+
+    fn move_to_str(&self, mv: KuhnMove) -> String {
+        match mv {
+            KuhnMove::Deal(p1, p2) => format!("deal {} {}", p1, p2),
+            KuhnMove::Check => String::from("check"),
+            KuhnMove::Bet => String::from("bet"),
+            KuhnMove::Call => String::from("call"),
+            KuhnMove::Fold => String::from("fold"),
+        }
+    }
+
+    fn chance_outcomes(&self) -> Vec<(KuhnMove, f32)> {
+        if self.cards.is_some() {
+            return vec![];
+        }
+        let mut outcomes = vec![];
+        for p1 in 0..CARDS {
+            for p2 in (0..CARDS).filter(|p2| *p2 != p1) {
+                outcomes.push((KuhnMove::Deal(p1, p2), 1f32));
+            }
+        }
+        let deals = outcomes.len() as f32;
+        for outcome in &mut outcomes {
+            outcome.1 /= deals;
+        }
+        outcomes
+    }
+
+    /// Deals the opponent one of the cards `observer` doesn't hold.
+    fn determinize(&self, observer: Player) -> Self {
+        let mut board = *self;
+        if let Some(cards) = &mut board.cards {
+            let own = cards[observer.index()];
+            let others = (0..CARDS).filter(|card| *card != own).collect::<Vec<_>>();
+            let other = others[rand::thread_rng().gen_range(0..others.len())];
+            cards[observer.next(2).index()] = other;
+        }
+        board
+    }
+
+    fn terminal_score(&self) -> Option<f32> {
+        match self.game_state() {
+            GameState::End(Some(Player::P1)) => Some(self.pot[1] as f32),
+            GameState::End(Some(Player::P2)) => Some(-(self.pot[0] as f32)),
+            _ => None,
+        }
+    }
+}
@@ -4,7 +4,7 @@ use crate::barracuda::b_core::algorithms::extensions::t_table::TranspositionTabl
 use crate::barracuda::b_core::algorithms::policy::uniform::Uniform;
 use crate::barracuda::b_core::algorithms::simulate::random_playout::RandomPlayout;
 use crate::barracuda::b_core::algorithms::ucb::exploration::Exploration;
-use crate::barracuda::b_core::algorithms::ucb::formula::{Add, Const, Mul};
+use crate::barracuda::b_core::algorithms::ucb::expression::FormulaRegistry;
 use crate::barracuda::b_core::algorithms::ucb::random::Random;
 use crate::barracuda::b_core::algorithms::ucb::value::Value;
 use crate::barracuda::b_core::components::ucb::Ucb;
@@ -25,12 +25,12 @@ use text_io::read;
 mod barracuda;
 mod cf;
 mod cli;
+mod kuhn;
+mod pig;
 mod tictactoe;
 
 /*
 TODO: Add a proper CLI
-TODO: Parallelize Search
-TODO: Work on UCB Expression Tree
 TODO: Independent Barracuda GUI (Made in any language)
 TODO: Independent Rust Matrix Lib (Should be able to run on most GPUs and fallback on CPU when necessary)
 TODO: Modular Alpha Beta Search System
@@ -38,29 +38,26 @@ TODO: Implement more Barracuda-Compatible Board Games
  */
 
 fn main() {
-    let value = Arc::new(Mutex::new(Value::new()));
-    let exploration = Arc::new(Mutex::new(Exploration::new(1f32)));
     let mast = Arc::new(Mutex::new(Mast::new(0.05)));
     let rave = Arc::new(Mutex::new(Rave::new(0, 1)));
     let random = Arc::new(Mutex::new(Random::new(1e-3)));
     let t_table = Arc::new(Mutex::new(TranspositionTable::new()));
 
+    let mut registry = FormulaRegistry::new();
+    registry.register("value", Arc::new(Mutex::new(Value::new())));
+    registry.register("explore", Arc::new(Mutex::new(Exploration::new(1f32))));
+    registry.register("mast", mast.clone());
+    registry.register("rave", rave.clone());
+    registry.register_formula("random", random);
+    registry.register_formula("t_table", t_table.clone());
+    let expression = registry
+        .parse("random + t_table + value + explore + mast + rave * 0.1")
+        .unwrap();
+
     let mut br_all_features = BarracudaRunner::<TicTacToeBoard, TicTacToeParams, Square, 4>::new(
         BarracudaAlgorithms {
-            ucb_algorithms: vec![
-                value.clone(),
-                exploration.clone(),
-                mast.clone(),
-                rave.clone(),
-            ],
-            ucb: Ucb::new(Arc::new(Mutex::new(Add::new(vec![
-                random,
-                t_table.clone(),
-                value,
-                exploration,
-                mast.clone(),
-                Arc::new(Mutex::new(Mul::new(vec![rave.clone(), Const::new(0.1f32)]))),
-            ])))),
+            ucb_algorithms: expression.ucb_algorithms,
+            ucb: Ucb::new(expression.formula),
             policy: Arc::new(Mutex::new(Uniform::new())),
             simulation_algorithms: vec![mast, rave],
             simulation: Arc::new(Mutex::new(RandomPlayout::new(
@@ -0,0 +1,118 @@
+use crate::barracuda::traits::{BarracudaBoard, BarracudaMove, BarracudaParams, GameState, Player};
+
+const SIDES: u32 = 6;
+
+pub struct PigParams {
+    target: u32,
+}
+
+impl BarracudaParams for PigParams {}
+
+impl Default for PigParams {
+    fn default() -> Self {
+        Self { target: 100 }
+    }
+}
+
+impl PigParams {
+    pub fn new(target: u32) -> Self {
+        Self { target }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum PigMove {
+    Roll,
+    Hold,
+    Die(u32),
+}
+
+impl BarracudaMove for PigMove {}
+
+/// Players roll a die as long as they like, adding the rolls to their turn total. Holding banks
+/// the turn total, rolling a one loses it and ends the turn. First to reach the target wins.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PigBoard {
+    scores: [u32; 2],
+    turn_total: u32,
+    turn: Player,
+    rolling: bool,
+    target: u32,
+}
+
+impl PigBoard {
+    fn end_turn(&mut self) {
+        self.turn_total = 0;
+        self.turn = self.turn.next(2);
+    }
+}
+
+impl BarracudaBoard<PigParams, PigMove> for PigBoard {
+    fn new(params: PigParams) -> Self {
+        Self {
+            scores: [0; 2],
+            turn_total: 0,
+            turn: Player::P1,
+            rolling: false,
+            target: params.target,
+        }
+    }
+
+    fn make_move(&mut self, mv: PigMove) {
+        match mv {
+            PigMove::Roll => self.rolling = true,
+            PigMove::Hold => {
+                self.scores[self.turn.index()] += self.turn_total;
+                self.end_turn();
+            }
+            PigMove::Die(roll) => {
+                self.rolling = false;
+                if roll == 1 {
+                    self.end_turn();
+                } else {
+                    self.turn_total += roll;
+                }
+            }
+        }
+    }
+
+    fn get_moves(&self) -> Vec<PigMove> {
+        if self.rolling || !matches!(self.game_state(), GameState::Ongoing) {
+            vec![]
+        } else {
+            vec![PigMove::Roll, PigMove::Hold]
+        }
+    }
+
+    fn game_state(&self) -> GameState {
+        if self.scores[0] >= self.target {
+            GameState::End(Some(Player::P1))
+        } else if self.scores[1] >= self.target {
+            GameState::End(Some(Player::P2))
+        } else {
+            GameState::Ongoing
+        }
+    }
+
+    fn turn(&self) -> Player {
+        self.turn
+    }
+
+    fn move_to_str(&self, mv: PigMove) -> String {
+        match mv {
+            PigMove::Roll => String::from("roll"),
+            PigMove::Hold => String::from("hold"),
+            PigMove::Die(roll) => format!("{}", roll),
+        }
+    }
+
+    fn chance_outcomes(&self) -> Vec<(PigMove, f32)> {
+        if self.rolling {
+            (1..=SIDES)
+                .map(|roll| (PigMove::Die(roll), 1f32 / SIDES as f32))
+                .collect()
+        } else {
+            vec![]
+        }
+    }
+}
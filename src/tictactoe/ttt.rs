@@ -20,6 +20,12 @@ pub struct Square {
 
 impl BarracudaMove for Square {}
 
+impl Square {
+    pub fn new(x: usize, y: usize) -> Self {
+        Self { x, y }
+    }
+}
+
 impl BarracudaBoard<TicTacToeParams, Square> for TicTacToeBoard {
     fn new(_: TicTacToeParams) -> Self {
         Self {
@@ -31,10 +37,7 @@ impl BarracudaBoard<TicTacToeParams, Square> for TicTacToeBoard {
 
     fn make_move(&mut self, mv: Square) {
         self.board[mv.x][mv.y] = Some(self.turn);
-        self.turn = match self.turn {
-            Player::P1 => Player::P2,
-            Player::P2 => Player::P1,
-        };
+        self.turn = self.turn.next(2);
         self.cnt += 1;
     }
 